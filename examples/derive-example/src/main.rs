@@ -32,7 +32,7 @@ fn main() {
     println!("Event Name: {:?}", message.event_name());
     println!("Event Name: {:?}", message);
 
-    let mut event_bus = SynchronousEventBus::new();
+    let event_bus = SynchronousEventBus::new();
     event_bus.register(Rc::new(UpdateTotalMessagesSent));
 
     event_bus.publish(message);
@@ -0,0 +1,80 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio_postgres::NoTls;
+
+use hermes::bus::AsynchronousEventBus;
+use hermes::bus::postgres_bus::PostgresEventBus;
+use hermes::consumer::AsyncConsumer;
+use hermes::consumer::postgres_consumer::PostgresConsumer;
+use hermes::consumer::postgres_retryer::PostgresRetryer;
+use hermes::derive::{Event, EventMetadata};
+use hermes::event::EventMetadata;
+use hermes::impl_payload_handler;
+use hermes::postgres::postgres_configurer::PostgresConfigurer;
+use hermes::postgres::postgres_listener::PostgresListener;
+use hermes::postgres::postgres_publisher::PostgresPublisher;
+use hermes::serializer::serde_formatter::SerdeJSONEventFormatter;
+use hermes::subscriber::SubscriberError;
+
+#[derive(Debug, EventMetadata, Serialize, Deserialize, Event)]
+struct ChatMessageSent {
+    pub message: String,
+    pub user: String,
+    pub metadata: EventMetadata
+}
+
+struct SendNotificationOnChatMessageSent;
+
+impl SendNotificationOnChatMessageSent {
+    async fn on_chat_message_sent(&self, event: &ChatMessageSent) -> Result<(), SubscriberError> {
+        println!("Handling message sent: {:?}", event);
+        Ok(())
+    }
+}
+
+impl_payload_handler!(
+    SendNotificationOnChatMessageSent,
+    (ChatMessageSent, on_chat_message_sent)
+);
+
+#[tokio::main]
+async fn main() {
+    let (client, connection) = tokio_postgres::connect("host=localhost user=postgres password=postgres", NoTls).await.unwrap();
+    let listener = PostgresListener::spawn(connection);
+    let client = Arc::new(client);
+
+    let configurer = PostgresConfigurer::new(client.clone(), "events".to_string());
+    configurer.configure(("SendNotificationOnChatMessageSent", &["chat_message_sent"])).await;
+
+    let formatter = SerdeJSONEventFormatter;
+    let publisher = Arc::new(PostgresPublisher::new(client.clone()));
+    let event_bus = PostgresEventBus::new(
+        publisher,
+        &formatter,
+        "events".to_string()
+    );
+
+    let event = ChatMessageSent {
+        message: "new message".to_string(),
+        user: "user".to_string(),
+        metadata: EventMetadata::default()
+    };
+
+    event_bus.publish(event).await.expect("Cannot publish event");
+
+    let retryer = PostgresRetryer::new(3, Duration::from_secs(10));
+    let mut consumer = PostgresConsumer::new(
+        client,
+        listener,
+        "events",
+        "SendNotificationOnChatMessageSent",
+        &formatter,
+        SendNotificationOnChatMessageSent,
+        &retryer
+    );
+
+    consumer.consume().await;
+}
@@ -33,12 +33,27 @@ pub fn event_derive(input: TokenStream) -> TokenStream {
     let name = &input.ident;
     let event_name = convert_case::Casing::to_case(&name.to_string(), convert_case::Case::Snake);
     let event_name_literal = syn::LitStr::new(&event_name, name.span());
+    let metadata_mut = if has_metadata_field(&input.data) {
+        quote::quote! {
+            fn metadata_mut(&mut self) -> Option<&mut hermes::event::EventMetadata> {
+                Some(&mut self.metadata)
+            }
+
+            fn event_metadata(&self) -> Option<&hermes::event::EventMetadata> {
+                Some(&self.metadata)
+            }
+        }
+    } else {
+        quote::quote! {}
+    };
 
     let expanded = quote::quote! {
         impl hermes::event::Event for #name {
             fn event_name(&self) -> &'static str {
                 <Self as hermes::event::EventName>::static_event_name()
             }
+
+            #metadata_mut
         }
 
         impl hermes::event::EventName for #name {
@@ -49,3 +64,19 @@ pub fn event_derive(input: TokenStream) -> TokenStream {
     };
     TokenStream::from(expanded)
 }
+
+fn has_metadata_field(data: &syn::Data) -> bool {
+    let syn::Data::Struct(data) = data else {
+        return false;
+    };
+
+    data.fields.iter().any(|field| {
+        let is_metadata = field.ident.as_ref().is_some_and(|ident| ident == "metadata");
+        let is_event_metadata = match &field.ty {
+            syn::Type::Path(path) => path.path.segments.last().is_some_and(|segment| segment.ident == "EventMetadata"),
+            _ => false,
+        };
+
+        is_metadata && is_event_metadata
+    })
+}
@@ -0,0 +1,398 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::aggregate::AggregateRoot;
+#[cfg(feature = "async")]
+use crate::bus::AsynchronousEventBus;
+use crate::bus::EventBus;
+use crate::event::Event;
+use crate::event_store::{EventStore, EventStoreError, ExpectedVersion, NewEvent, StoredEvent};
+use crate::event_store::snapshot_store::{Snapshot, SnapshotPolicy, SnapshotStore};
+use crate::serializer::{EventDeserializer, EventSerializer, SnapshotSerializer};
+
+type LoadSnapshotClosure<'a, A> = Box<dyn Fn(&str) -> Result<Option<A>, EventStoreError> + 'a>;
+
+type SaveSnapshotClosure<'a, A> = Box<dyn Fn(&str, &A) -> Result<(), EventStoreError> + 'a>;
+
+struct Snapshotter<'a, A> {
+    load: LoadSnapshotClosure<'a, A>,
+    save: SaveSnapshotClosure<'a, A>,
+    policy: SnapshotPolicy,
+}
+
+///
+/// Loads and saves event-sourced aggregates through an `EventStore`.
+///
+/// Each aggregate is stored in the stream `<aggregate_type>-<aggregate_id>`.
+///
+pub struct EventSourcedRepository<'a, A, S, F>
+where
+    A: AggregateRoot,
+    S: EventStore,
+    F: EventSerializer + EventDeserializer
+{
+    store: Arc<S>,
+    formatter: &'a F,
+    snapshotter: Option<Snapshotter<'a, A>>,
+    marker: PhantomData<A>,
+}
+
+impl<'a, A, S, F> EventSourcedRepository<'a, A, S, F>
+where
+    A: AggregateRoot,
+    S: EventStore,
+    F: EventSerializer + EventDeserializer
+{
+    pub fn new(store: Arc<S>, formatter: &'a F) -> Self {
+        Self {
+            store,
+            formatter,
+            snapshotter: None,
+            marker: PhantomData,
+        }
+    }
+
+    ///
+    /// Take snapshots of the aggregates following the given policy.
+    ///
+    /// Loading an aggregate then starts from its latest snapshot and only reads the
+    /// events stored after it. The aggregate is serialized with the repository formatter,
+    /// so its `AggregateContext` must be skipped, e.g. with `#[serde(skip)]`.
+    ///
+    pub fn with_snapshots<SS>(mut self, snapshot_store: Arc<SS>, policy: SnapshotPolicy) -> Self
+    where
+        SS: SnapshotStore + 'static,
+        A: Serialize + DeserializeOwned,
+        F: SnapshotSerializer
+    {
+        let formatter = self.formatter;
+        let load_store = snapshot_store.clone();
+
+        let load: LoadSnapshotClosure<'a, A> = Box::new(move |stream_id| {
+            let snapshot = match load_store.load(stream_id)? {
+                Some(snapshot) => snapshot,
+                None => return Ok(None),
+            };
+
+            let mut aggregate = formatter.deserialize_snapshot::<A>(&snapshot.payload)
+                                         .map_err(|e| EventStoreError::CannotDeserializeSnapshot(Box::new(e)))?;
+            aggregate.context_mut().set_version(snapshot.version);
+
+            Ok(Some(aggregate))
+        });
+
+        let save: SaveSnapshotClosure<'a, A> = Box::new(move |stream_id, aggregate| {
+            let payload = formatter.serialize_snapshot(aggregate)
+                                   .map_err(|e| EventStoreError::CannotSerializeSnapshot(Box::new(e)))?;
+
+            snapshot_store.save(Snapshot {
+                stream_id: stream_id.to_string(),
+                version: aggregate.version(),
+                payload,
+            })
+        });
+
+        self.snapshotter = Some(Snapshotter { load, save, policy });
+        self
+    }
+
+    pub fn stream_id(aggregate_id: &str) -> String {
+        format!("{}-{}", A::aggregate_type(), aggregate_id)
+    }
+
+    pub fn load(&self, aggregate_id: &str) -> Result<Option<A>, EventStoreError> {
+        let stream_id = Self::stream_id(aggregate_id);
+
+        let snapshot = match &self.snapshotter {
+            Some(snapshotter) => (snapshotter.load)(&stream_id)?,
+            None => None,
+        };
+
+        let from_version = snapshot.as_ref().map_or(1, |aggregate| aggregate.version() + 1);
+        let stored_events = self.store.read_stream(&stream_id, from_version)?;
+
+        if snapshot.is_none() && stored_events.is_empty() {
+            return Ok(None);
+        }
+
+        let events = stored_events.into_iter()
+                                  .map(|event| self.deserialize(event))
+                                  .collect::<Result<Vec<_>, _>>()?;
+
+        let aggregate = match snapshot {
+            Some(mut aggregate) => {
+                aggregate.replay(events);
+                aggregate
+            },
+            None => A::rehydrate(events),
+        };
+
+        Ok(Some(aggregate))
+    }
+
+    ///
+    /// Append the recorded events of the aggregate to its stream and return them,
+    /// so they can be published through any event bus.
+    ///
+    pub fn save(&self, aggregate: &mut A) -> Result<Vec<A::Event>, EventStoreError> {
+        let new_events = aggregate.context()
+                                  .recorded_events()
+                                  .iter()
+                                  .map(|event| {
+                                      let payload = self.formatter.serialize(event)
+                                                        .map_err(|e| EventStoreError::CannotSerializeEvent(Box::new(e)))?;
+
+                                      Ok(NewEvent::new(event.event_name().to_string(), payload))
+                                  })
+                                  .collect::<Result<Vec<_>, _>>()?;
+
+        if new_events.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let expected_version = match aggregate.version() {
+            0 => ExpectedVersion::NoStream,
+            version => ExpectedVersion::Exact(version),
+        };
+
+        let previous_version = aggregate.version();
+        let stream_id = Self::stream_id(&aggregate.aggregate_id());
+        let version = self.store.append(&stream_id, expected_version, new_events)?;
+        aggregate.context_mut().set_version(version);
+
+        let events = aggregate.pull_domain_events();
+
+        if let Some(snapshotter) = &self.snapshotter {
+            if snapshotter.policy.should_snapshot(previous_version, version) {
+                if let Err(e) = (snapshotter.save)(&stream_id, aggregate) {
+                    log::error!("Error while saving snapshot of {}: {:?}", stream_id, e);
+                }
+            }
+        }
+
+        Ok(events)
+    }
+
+    ///
+    /// Save the aggregate and publish its events through a synchronous event bus.
+    ///
+    pub fn save_and_publish<B: EventBus>(&self, aggregate: &mut A, event_bus: &B) -> Result<(), EventStoreError> {
+        for event in self.save(aggregate)? {
+            event_bus.publish(event);
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Save the aggregate and publish its events through an asynchronous event bus.
+    ///
+    #[cfg(feature = "async")]
+    pub async fn save_and_publish_async<B: AsynchronousEventBus>(&self, aggregate: &mut A, event_bus: &B) -> Result<(), EventStoreError> {
+        for event in self.save(aggregate)? {
+            event_bus.publish(event)
+                     .await
+                     .map_err(|e| EventStoreError::CannotPublishEvent(Box::new(e)))?;
+        }
+
+        Ok(())
+    }
+
+    fn deserialize(&self, event: StoredEvent) -> Result<A::Event, EventStoreError> {
+        self.formatter
+            .deserialize::<A::Event>(&event.payload)
+            .map(|deserialized| deserialized.data.attributes)
+            .map_err(|e| EventStoreError::CannotDeserializeEvent(Box::new(e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::aggregate::AggregateContext;
+    use crate::event::{DomainEvent, EventMetadata};
+    use crate::event_store::in_memory_event_store::InMemoryEventStore;
+    use crate::event_store::snapshot_store::InMemorySnapshotStore;
+    use crate::serializer::serde_formatter::SerdeJSONEventFormatter;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    enum AccountChange {
+        Opened { id: String },
+        Deposited { amount: u64 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    struct AccountEvent {
+        change: AccountChange,
+        metadata: EventMetadata
+    }
+
+    impl AccountEvent {
+        fn new(change: AccountChange) -> Self {
+            Self { change, metadata: EventMetadata::default() }
+        }
+    }
+
+    impl Event for AccountEvent {
+        fn event_name(&self) -> &'static str {
+            "account_event"
+        }
+    }
+
+    impl DomainEvent for AccountEvent {}
+
+    crate::event_metadata!(AccountEvent);
+
+    #[derive(Default, Serialize, Deserialize)]
+    struct Account {
+        id: String,
+        balance: u64,
+        #[serde(skip)]
+        context: AggregateContext<AccountEvent>,
+    }
+
+    impl Account {
+        fn open(id: &str) -> Self {
+            let mut account = Account::default();
+            account.record(AccountEvent::new(AccountChange::Opened { id: id.to_string() }));
+            account
+        }
+
+        fn deposit(&mut self, amount: u64) {
+            self.record(AccountEvent::new(AccountChange::Deposited { amount }));
+        }
+    }
+
+    impl AggregateRoot for Account {
+        type Event = AccountEvent;
+
+        fn aggregate_type() -> &'static str {
+            "account"
+        }
+
+        fn aggregate_id(&self) -> String {
+            self.id.clone()
+        }
+
+        fn apply(&mut self, event: &AccountEvent) {
+            match &event.change {
+                AccountChange::Opened { id } => self.id = id.clone(),
+                AccountChange::Deposited { amount } => self.balance += amount,
+            }
+        }
+
+        fn context(&self) -> &AggregateContext<AccountEvent> {
+            &self.context
+        }
+
+        fn context_mut(&mut self) -> &mut AggregateContext<AccountEvent> {
+            &mut self.context
+        }
+    }
+
+    #[test]
+    fn it_should_save_and_rehydrate_an_aggregate() {
+        let store = Arc::new(InMemoryEventStore::new());
+        let repository: EventSourcedRepository<Account, _, _> = EventSourcedRepository::new(store.clone(), &SerdeJSONEventFormatter);
+
+        let mut account = Account::open("1");
+        account.deposit(10);
+        let saved_events = repository.save(&mut account).unwrap();
+
+        assert_eq!(saved_events.len(), 2);
+        assert_eq!(account.version(), 2);
+
+        let mut account = repository.load("1").unwrap().unwrap();
+        account.deposit(5);
+        repository.save(&mut account).unwrap();
+
+        let account = repository.load("1").unwrap().unwrap();
+        assert_eq!(account.balance, 15);
+        assert_eq!(account.version(), 3);
+        assert!(repository.load("2").unwrap().is_none());
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn it_should_save_and_rehydrate_an_aggregate_with_a_binary_formatter() {
+        use crate::serializer::msgpack_formatter::MessagePackEventFormatter;
+
+        let store = Arc::new(InMemoryEventStore::new());
+        let snapshots = Arc::new(InMemorySnapshotStore::new());
+        let repository: EventSourcedRepository<Account, _, _> = EventSourcedRepository::new(store, &MessagePackEventFormatter)
+            .with_snapshots(snapshots.clone(), SnapshotPolicy::EveryNEvents(2));
+
+        let mut account = Account::open("1");
+        account.deposit(10);
+        repository.save(&mut account).unwrap();
+
+        assert!(snapshots.load("account-1").unwrap().is_some());
+        let account = repository.load("1").unwrap().unwrap();
+        assert_eq!(account.balance, 10);
+        assert_eq!(account.version(), 2);
+    }
+
+    #[test]
+    fn it_should_not_save_an_aggregate_modified_concurrently() {
+        let store = Arc::new(InMemoryEventStore::new());
+        let repository: EventSourcedRepository<Account, _, _> = EventSourcedRepository::new(store, &SerdeJSONEventFormatter);
+
+        repository.save(&mut Account::open("1")).unwrap();
+
+        let mut first = repository.load("1").unwrap().unwrap();
+        let mut second = repository.load("1").unwrap().unwrap();
+        first.deposit(1);
+        second.deposit(2);
+
+        repository.save(&mut first).unwrap();
+        let result = repository.save(&mut second);
+
+        assert!(matches!(result, Err(EventStoreError::WrongExpectedVersion { .. })));
+        assert_eq!(second.context().recorded_events().len(), 1);
+    }
+
+    #[test]
+    fn it_should_load_an_aggregate_from_its_latest_snapshot() {
+        let store = Arc::new(InMemoryEventStore::new());
+        let snapshots = Arc::new(InMemorySnapshotStore::new());
+        let repository: EventSourcedRepository<Account, _, _> = EventSourcedRepository::new(store.clone(), &SerdeJSONEventFormatter)
+            .with_snapshots(snapshots.clone(), SnapshotPolicy::EveryNEvents(2));
+
+        let mut account = Account::open("1");
+        account.deposit(10);
+        repository.save(&mut account).unwrap();
+        account.deposit(5);
+        repository.save(&mut account).unwrap();
+
+        let snapshot = snapshots.load("account-1").unwrap().unwrap();
+        assert_eq!(snapshot.version, 2);
+
+        store.append("account-1", ExpectedVersion::Exact(3), vec![
+            NewEvent::new("account_event".to_string(), SerdeJSONEventFormatter.serialize(&AccountEvent::new(AccountChange::Deposited { amount: 1 })).unwrap())
+        ]).unwrap();
+
+        let account = repository.load("1").unwrap().unwrap();
+        assert_eq!(account.balance, 16);
+        assert_eq!(account.version(), 4);
+    }
+
+    #[test]
+    fn it_should_report_a_snapshot_that_cannot_be_deserialized() {
+        let store = Arc::new(InMemoryEventStore::new());
+        let snapshots = Arc::new(InMemorySnapshotStore::new());
+        let repository: EventSourcedRepository<Account, _, _> = EventSourcedRepository::new(store, &SerdeJSONEventFormatter)
+            .with_snapshots(snapshots.clone(), SnapshotPolicy::EveryNEvents(2));
+
+        snapshots.save(Snapshot { stream_id: "account-1".to_string(), version: 2, payload: b"not a snapshot".to_vec() }).unwrap();
+
+        let result = repository.load("1");
+
+        assert!(matches!(result, Err(EventStoreError::CannotDeserializeSnapshot(_))));
+    }
+}
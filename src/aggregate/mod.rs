@@ -0,0 +1,107 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::event::{DomainEvent, EventWithMetadata};
+
+pub mod event_sourced_repository;
+
+///
+/// Keeps the persisted version of an aggregate and the domain events recorded since it was loaded.
+///
+#[derive(Debug)]
+pub struct AggregateContext<E> {
+    version: u64,
+    recorded_events: Vec<E>,
+}
+
+impl<E> Default for AggregateContext<E> {
+    fn default() -> Self {
+        Self {
+            version: 0,
+            recorded_events: vec![],
+        }
+    }
+}
+
+impl<E> AggregateContext<E> {
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn record(&mut self, event: E) {
+        self.recorded_events.push(event);
+    }
+
+    pub fn recorded_events(&self) -> &[E] {
+        &self.recorded_events
+    }
+
+    pub(crate) fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+
+    pub(crate) fn drain(&mut self) -> Vec<E> {
+        std::mem::take(&mut self.recorded_events)
+    }
+}
+
+///
+/// An aggregate whose state is the result of applying its domain events.
+///
+/// Implementors keep an `AggregateContext` and mutate their state only from `apply`,
+/// so the same code path is used both to record new events and to rehydrate.
+///
+pub trait AggregateRoot: Default + Send + Sync + 'static {
+    type Event: DomainEvent + EventWithMetadata + Serialize + DeserializeOwned;
+
+    fn aggregate_type() -> &'static str;
+
+    fn aggregate_id(&self) -> String;
+
+    fn apply(&mut self, event: &Self::Event);
+
+    fn context(&self) -> &AggregateContext<Self::Event>;
+
+    fn context_mut(&mut self) -> &mut AggregateContext<Self::Event>;
+
+    ///
+    /// Apply a new event and keep it to be saved.
+    ///
+    fn record(&mut self, event: Self::Event) {
+        self.apply(&event);
+        self.context_mut().record(event);
+    }
+
+    ///
+    /// The version of the aggregate in the event store, without the recorded events.
+    ///
+    fn version(&self) -> u64 {
+        self.context().version()
+    }
+
+    fn pull_domain_events(&mut self) -> Vec<Self::Event> {
+        self.context_mut().drain()
+    }
+
+    ///
+    /// Rebuild an aggregate from the events of its stream.
+    ///
+    fn rehydrate<I: IntoIterator<Item = Self::Event>>(events: I) -> Self {
+        let mut aggregate = Self::default();
+        aggregate.replay(events);
+
+        aggregate
+    }
+
+    ///
+    /// Apply events already stored after the current version, e.g. the ones after a snapshot.
+    ///
+    fn replay<I: IntoIterator<Item = Self::Event>>(&mut self, events: I) {
+        for event in events {
+            self.apply(&event);
+
+            let version = self.version() + 1;
+            self.context_mut().set_version(version);
+        }
+    }
+}
@@ -1,28 +1,187 @@
-use std::any::TypeId;
-use std::collections::HashMap;
+use std::any::{type_name, TypeId};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::future::Future;
+use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use arc_swap::ArcSwap;
 use downcaster::{Downcast, downcast_ref};
+use futures_lite::FutureExt;
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+use tokio::task::{JoinError, JoinHandle};
 
 use crate::bus::AsynchronousEventBus;
+use crate::bus::dispatch_queue::{DispatchFuture, DispatchQueue, OverflowPolicy};
 use crate::bus::error::PublishError;
+use crate::bus::event_pattern::EventPattern;
+use crate::bus::failure::{HandlerPanicked, PanicHandler, report_panic};
+use crate::bus::middleware::{Middleware, MiddlewareAction, MiddlewareStack};
+use crate::bus::ordering::{LaneGuard, Lanes, OrderingKey};
+use crate::bus::retry::{DeadLetter, DeadLetterSink, RetryPolicy};
+use crate::bus::subscription::{HandlerExecution, SubscriberOptions, SubscriberRegistry, SubscriptionId, Unsubscribe};
 use crate::event::Event;
-use crate::subscriber::AsyncSubscriber;
+use crate::subscriber::{AsyncAnySubscriber, AsyncSubscriber, SubscriberError};
 
-type SubscriberClosure = Box<dyn Fn(Arc<dyn Event>) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+type SubscriberClosure = Box<dyn Fn(Arc<dyn Event>) -> Pin<Box<dyn Future<Output = Result<(), SubscriberError>> + Send>> + Send + Sync>;
+
+struct Registration {
+    subscriber: &'static str,
+    timeout: Option<Duration>,
+    concurrency: Option<Arc<Semaphore>>,
+    retry_policy: Option<RetryPolicy>,
+    handler: SubscriberClosure,
+}
+
+type Registry = SubscriberRegistry<Arc<Registration>>;
 
 ///
 /// An asynchronous event bus that handles events asynchronously.
 ///
+/// Subscribers can be registered at any time on a shared bus. Registering copies the subscribers
+/// and swaps them in, so publishing never waits for a lock.
+///
 #[derive(Default)]
 pub struct TokioEventBus {
-    subscribers: HashMap<TypeId, Vec<SubscriberClosure>>
+    subscribers: ArcSwap<Registry>,
+    registering: Mutex<()>,
+    middlewares: Arc<MiddlewareStack>,
+    handler_execution: HandlerExecution,
+    handler_timeout: Option<Duration>,
+    concurrency: Option<Arc<Semaphore>>,
+    queue: Option<Arc<DispatchQueue>>,
+    panic_handler: Option<PanicHandler>,
+    ordering_key: OrderingKey,
+    lanes: Arc<Lanes<DispatchFuture>>,
+    retry_policy: Option<RetryPolicy>,
+    dead_letters: Option<Arc<DeadLetterSink>>,
+}
+
+///
+/// The error a subscriber fails with when it is cancelled for taking too long.
+///
+#[derive(Debug)]
+pub struct HandlerTimedOut {
+    pub subscriber: &'static str,
+    pub timeout: Duration,
+}
+
+impl Display for HandlerTimedOut {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} timed out after {:?}", self.subscriber, self.timeout)
+    }
+}
+
+impl Error for HandlerTimedOut {}
+
+///
+/// Completes once every subscriber of a detached publish has handled the event.
+///
+pub struct PublishHandle {
+    dispatch: Option<JoinHandle<()>>,
+}
+
+impl PublishHandle {
+    pub fn is_finished(&self) -> bool {
+        match &self.dispatch {
+            Some(dispatch) => dispatch.is_finished(),
+            None => true,
+        }
+    }
+
+    pub async fn wait(self) {
+        if let Some(dispatch) = self.dispatch {
+            if let Err(e) = dispatch.await {
+                log::error!("Error while processing event: {:?}", e);
+            }
+        }
+    }
 }
 
 impl TokioEventBus {
-    pub fn register<E, S>(&mut self, subscriber: Arc<S>)
+    pub fn with_handler_execution(mut self, handler_execution: HandlerExecution) -> Self {
+        self.handler_execution = handler_execution;
+        self
+    }
+
+    ///
+    /// Cancel subscribers taking longer than the timeout, unless they were registered with their own.
+    ///
+    pub fn with_handler_timeout(mut self, handler_timeout: Duration) -> Self {
+        self.handler_timeout = Some(handler_timeout);
+        self
+    }
+
+    ///
+    /// Limit how many subscribers run at the same time across all events. Publishing waits for a
+    /// free slot before spawning a subscriber.
+    ///
+    pub fn with_max_concurrent_handlers(mut self, max_concurrent_handlers: usize) -> Self {
+        self.concurrency = Some(Arc::new(Semaphore::new(max_concurrent_handlers)));
+        self
+    }
+
+    ///
+    /// Queue published events, handling them one after the other in a background task.
+    ///
+    /// `publish` returns once the event is queued, and the `overflow_policy` decides what happens
+    /// when the queue is full. Detached publishes skip the queue.
+    ///
+    pub fn with_bounded_queue(mut self, capacity: usize, overflow_policy: OverflowPolicy) -> Self {
+        self.queue = Some(Arc::new(DispatchQueue::new(capacity, overflow_policy)));
+        self
+    }
+
+    ///
+    /// Call `panic_handler` with every subscriber panic, on top of logging it.
+    ///
+    pub fn with_panic_handler(mut self, panic_handler: impl Fn(&HandlerPanicked) + Send + Sync + 'static) -> Self {
+        self.panic_handler = Some(Arc::new(panic_handler));
+        self
+    }
+
+    ///
+    /// Run failing subscribers again, unless they were registered with their own retry policy.
+    ///
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    ///
+    /// Keep the events subscribers failed to handle, once they are not retried anymore.
+    ///
+    pub fn with_dead_letter_sink(mut self, dead_letters: Arc<DeadLetterSink>) -> Self {
+        self.dead_letters = Some(dead_letters);
+        self
+    }
+
+    ///
+    /// Take the ordering key of the events from somewhere else than `Event::ordering_key`.
+    ///
+    /// The subscribers of an event with a key only start once the ones of the previous event with
+    /// the same key are done. Queued events are already handled one after the other.
+    ///
+    pub fn with_ordering_key(mut self, ordering_key: OrderingKey) -> Self {
+        self.ordering_key = ordering_key;
+        self
+    }
+
+    pub fn add_middleware<M: Middleware>(&mut self, middleware: M) {
+        Arc::make_mut(&mut self.middlewares).push(middleware);
+    }
+
+    pub fn register<E, S>(&self, subscriber: Arc<S>) -> SubscriptionId
+    where
+        E: Event + Downcast + 'static,
+        S: AsyncSubscriber<E> + 'static
+    {
+        self.register_with(subscriber, SubscriberOptions::default())
+    }
+
+    pub fn register_with<E, S>(&self, subscriber: Arc<S>, options: SubscriberOptions) -> SubscriptionId
     where
         E: Event + Downcast + 'static,
         S: AsyncSubscriber<E> + 'static
@@ -34,47 +193,343 @@ impl TokioEventBus {
 
             Box::pin(async move {
                 let event = event.as_ref();
-                if let Some(event) = downcast_ref!(event, E) {
-                    match value.handle_event(event).await {
-                        Ok(_) => {},
-                        Err(e) => {
-                            log::error!("Error while processing event: {:?}", e);
-                        }
-                    }
+                match downcast_ref!(event, E) {
+                    Some(event) => value.handle_event(event).await,
+                    None => Ok(()),
                 }
             })
         });
 
-        self.subscribers
-            .entry(event_type)
-            .or_default()
-            .push(handler);
+        self.update_subscribers(|registry| {
+            registry.insert(event_type, &options, Arc::new(Registration {
+                subscriber: type_name::<S>(),
+                timeout: options.timeout(),
+                concurrency: options.max_concurrency().map(|permits| Arc::new(Semaphore::new(permits))),
+                retry_policy: options.retry_policy(),
+                handler
+            }))
+        })
+    }
+
+    ///
+    /// Register a subscriber for every published event.
+    ///
+    pub fn register_any<S: AsyncAnySubscriber>(&self, subscriber: Arc<S>) -> SubscriptionId {
+        self.register_pattern_with(EventPattern::any(), subscriber, SubscriberOptions::default())
+    }
+
+    ///
+    /// Register a subscriber for the events whose name matches the pattern.
+    ///
+    pub fn register_pattern<S: AsyncAnySubscriber>(&self, pattern: impl Into<EventPattern>, subscriber: Arc<S>) -> SubscriptionId {
+        self.register_pattern_with(pattern, subscriber, SubscriberOptions::default())
+    }
+
+    pub fn register_pattern_with<S: AsyncAnySubscriber>(&self, pattern: impl Into<EventPattern>, subscriber: Arc<S>, options: SubscriberOptions) -> SubscriptionId {
+        let handler: SubscriberClosure = Box::new(move |event| {
+            let value = subscriber.clone();
+
+            Box::pin(async move {
+                value.handle_any(event.as_ref()).await
+            })
+        });
+
+        self.update_subscribers(|registry| {
+            registry.insert_pattern(pattern.into(), &options, Arc::new(Registration {
+                subscriber: type_name::<S>(),
+                timeout: options.timeout(),
+                concurrency: options.max_concurrency().map(|permits| Arc::new(Semaphore::new(permits))),
+                retry_policy: options.retry_policy(),
+                handler
+            }))
+        })
+    }
+
+    fn update_subscribers<T>(&self, update: impl FnOnce(&mut Registry) -> T) -> T {
+        let _registering = self.registering.lock().unwrap();
+
+        let mut registry = Registry::clone(&self.subscribers.load());
+        let result = update(&mut registry);
+        self.subscribers.store(Arc::new(registry));
+
+        result
     }
 }
 
-impl AsynchronousEventBus for TokioEventBus {
-    async fn publish<T: Event>(&self, event: T) -> Result<(), PublishError> {
-        let event_type = TypeId::of::<T>();
+impl Unsubscribe for TokioEventBus {
+    fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        self.update_subscribers(|registry| registry.remove(id))
+    }
+}
+
+impl TokioEventBus {
+    ///
+    /// Publish an event without waiting for its subscribers, returning a handle to await them later.
+    ///
+    pub fn publish_detached<T: Event>(&self, event: T) -> PublishHandle {
+        PublishHandle {
+            dispatch: self.dispatch(event).map(|(key, dispatch)| tokio::spawn(self.in_lane(key, dispatch))),
+        }
+    }
+
+    ///
+    /// Run the dispatch after the previous ones of its key, completing once it is done.
+    ///
+    fn in_lane(&self, key: Option<String>, dispatch: impl Future<Output = ()> + Send + 'static) -> DispatchFuture {
+        let Some(key) = key else {
+            return Box::pin(dispatch);
+        };
+
+        let (done_tx, done_rx) = oneshot::channel();
+        let dispatch: DispatchFuture = Box::pin(async move {
+            dispatch.await;
+            let _ = done_tx.send(());
+        });
+
+        if let Some(dispatch) = self.lanes.enter(&key, dispatch) {
+            tokio::spawn(run_lane(self.lanes.clone(), key, dispatch));
+        }
+
+        Box::pin(async move {
+            let _ = done_rx.await;
+        })
+    }
+
+    fn dispatch<T: Event>(&self, event: T) -> Option<(Option<String>, impl Future<Output = ()> + Send + 'static)> {
+        let mut event = event;
+        if self.middlewares.before_publish(&mut event) == MiddlewareAction::Stop {
+            return None;
+        }
+
+        let key = self.ordering_key.key_of(&event);
+        let start = Instant::now();
         let event = Arc::new(event);
 
-        let mut join_handlers = vec![];
-        if let Some(handlers) = self.subscribers.get(&event_type) {
-            for handler in handlers {
+        let registrations: Vec<Arc<Registration>> = self.subscribers
+                                                        .load()
+                                                        .matching(TypeId::of::<T>(), event.event_name())
+                                                        .into_iter()
+                                                        .filter(|registration| {
+                                                            self.middlewares.before_handle(event.as_ref(), registration.subscriber) == MiddlewareAction::Continue
+                                                        })
+                                                        .collect();
+
+        let settings = HandlerSettings {
+            middlewares: self.middlewares.clone(),
+            timeout: self.handler_timeout,
+            retry_policy: self.retry_policy,
+            dead_letters: self.dead_letters.clone(),
+            panic_handler: self.panic_handler.clone(),
+        };
+        let handler_execution = self.handler_execution;
+        let concurrency = self.concurrency.clone();
+        let event_name = event.event_name();
+
+        Some((key, async move {
+            let mut join_handlers = vec![];
+            for registration in registrations {
+                let subscriber = registration.subscriber;
+                let permits = Permits::new(concurrency.clone(), registration.concurrency.clone());
+
+                let handler_settings = settings.clone();
                 let event = event.clone();
-                join_handlers.push(tokio::spawn(handler(event)));
+                let join_handler = tokio::spawn(async move {
+                    handle(registration, event, handler_settings, permits).await;
+                });
+
+                match handler_execution {
+                    HandlerExecution::Concurrent => join_handlers.push((subscriber, join_handler)),
+                    HandlerExecution::Sequential => {
+                        if let Err(e) = join_handler.await {
+                            report_join_error(event_name, subscriber, e, settings.panic_handler.as_ref());
+                        }
+                    }
+                }
             }
-        }
 
-        for join_handler in join_handlers {
-            match join_handler.await {
-                Ok(_) => {},
-                Err(e) => {
-                    log::error!("Error while processing event: {:?}", e);
+            for (subscriber, join_handler) in join_handlers {
+                if let Err(e) = join_handler.await {
+                    report_join_error(event_name, subscriber, e, settings.panic_handler.as_ref());
                 }
             }
+
+            settings.middlewares.after_publish(event.as_ref(), start.elapsed());
+        }))
+    }
+}
+
+///
+/// Run the dispatches of a key, one after the other.
+///
+async fn run_lane(lanes: Arc<Lanes<DispatchFuture>>, key: String, dispatch: DispatchFuture) {
+    let mut lane = LaneGuard::new(&lanes, &key);
+    let mut next = Some(dispatch);
+
+    while let Some(dispatch) = next {
+        dispatch.await;
+        next = lane.next();
+    }
+}
+
+fn report_join_error(event_name: &'static str, subscriber: &'static str, error: JoinError, panic_handler: Option<&PanicHandler>) {
+    log::error!("Error while processing event: {:?}", error);
+
+    if error.is_panic() {
+        let panicked = HandlerPanicked::new(event_name, subscriber, error.into_panic().as_ref());
+        report_panic(panic_handler, &panicked);
+    }
+}
+
+///
+/// The settings of the bus a subscriber is handled with.
+///
+#[derive(Clone)]
+struct HandlerSettings {
+    middlewares: Arc<MiddlewareStack>,
+    timeout: Option<Duration>,
+    retry_policy: Option<RetryPolicy>,
+    dead_letters: Option<Arc<DeadLetterSink>>,
+    panic_handler: Option<PanicHandler>,
+}
+
+///
+/// The permits of the bus and of the subscriber a handler runs with.
+///
+/// The permit of the subscriber is acquired first, so a handler waiting behind a busy subscriber
+/// does not take a permit of the bus from the other subscribers.
+///
+struct Permits {
+    bus: Option<Arc<Semaphore>>,
+    subscriber: Option<Arc<Semaphore>>,
+    bus_permit: Option<OwnedSemaphorePermit>,
+    subscriber_permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Permits {
+    fn new(bus: Option<Arc<Semaphore>>, subscriber: Option<Arc<Semaphore>>) -> Self {
+        Self {
+            bus,
+            subscriber,
+            bus_permit: None,
+            subscriber_permit: None,
         }
+    }
+
+    async fn acquire(&mut self) {
+        self.subscriber_permit = acquire(&self.subscriber).await;
+        self.bus_permit = acquire(&self.bus).await;
+    }
+
+    fn release(&mut self) {
+        self.bus_permit = None;
+        self.subscriber_permit = None;
+    }
+}
 
-        Ok(())
+async fn acquire(semaphore: &Option<Arc<Semaphore>>) -> Option<OwnedSemaphorePermit> {
+    match semaphore {
+        Some(semaphore) => semaphore.clone().acquire_owned().await.ok(),
+        None => None,
+    }
+}
+
+///
+/// Run a subscriber until it succeeds or is not retried anymore.
+///
+/// The permits are released while waiting to retry, so other handlers can run in the meantime.
+/// A panicking subscriber is not retried: the panic is reported and the event dead-lettered.
+///
+async fn handle(registration: Arc<Registration>, event: Arc<dyn Event>, settings: HandlerSettings, mut permits: Permits) {
+    permits.acquire().await;
+
+    let HandlerSettings { middlewares, timeout: handler_timeout, retry_policy, dead_letters, panic_handler } = settings;
+    let retry_policy = registration.retry_policy.or(retry_policy);
+    let handler_start = Instant::now();
+    let mut attempt = 1;
+    let mut requeues = 0;
+
+    let result = loop {
+        let next = registration.clone();
+        let handler = middlewares.around_handle_async(event.clone(), registration.subscriber, Box::new(move |event| (next.handler)(event)));
+        let subscriber = registration.subscriber;
+        let timeout = registration.timeout.or(handler_timeout);
+
+        let result = AssertUnwindSafe(async move {
+            match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, handler)
+                    .await
+                    .unwrap_or_else(|_| Err(SubscriberError::Inner(Box::new(HandlerTimedOut { subscriber, timeout })))),
+                None => handler.await,
+            }
+        }).catch_unwind().await;
+
+        let result = match result {
+            Ok(result) => result,
+            Err(payload) => {
+                let panicked = HandlerPanicked::new(event.event_name(), subscriber, payload.as_ref());
+                report_panic(panic_handler.as_ref(), &panicked);
+
+                break Err(SubscriberError::Inner(Box::new(panicked)));
+            },
+        };
+
+        let delay = match (&result, &retry_policy) {
+            (Err(SubscriberError::Requeue), Some(retry_policy)) if retry_policy.should_requeue(requeues) => {
+                requeues += 1;
+                log::warn!("Requeuing {} for the {} time", registration.subscriber, requeues);
+                retry_policy.backoff(requeues)
+            },
+            (Err(e), Some(retry_policy)) if retry_policy.should_retry(attempt, e) => {
+                log::warn!("Retrying {} after attempt {}: {:?}", registration.subscriber, attempt, e);
+                attempt += 1;
+                retry_policy.delay(attempt - 1, e)
+            },
+            _ => break result,
+        };
+
+        permits.release();
+        tokio::time::sleep(delay).await;
+        permits.acquire().await;
+    };
+
+    middlewares.after_handle(event.as_ref(), registration.subscriber, &result, handler_start.elapsed());
+
+    if let Err(e) = result {
+        log::error!("Error while processing event: {:?}", e);
+
+        if let Some(dead_letters) = dead_letters.filter(|_| !matches!(e, SubscriberError::UnrecoverableError | SubscriberError::Requeue)) {
+            dead_letters.push(DeadLetter {
+                event_name: event.event_name(),
+                subscriber: registration.subscriber,
+                attempts: attempt,
+                error: e.to_string(),
+                event,
+            });
+        }
+    }
+}
+
+impl Drop for TokioEventBus {
+    fn drop(&mut self) {
+        if let Some(queue) = &self.queue {
+            queue.stop();
+        }
+    }
+}
+
+impl AsynchronousEventBus for TokioEventBus {
+    async fn publish<T: Event>(&self, event: T) -> Result<(), PublishError> {
+        let Some((key, dispatch)) = self.dispatch(event) else {
+            return Ok(());
+        };
+
+        match &self.queue {
+            Some(queue) => queue.push(Box::pin(dispatch)).await,
+            None => {
+                self.in_lane(key, dispatch).await;
+                Ok(())
+            }
+        }
     }
 }
 
@@ -153,13 +608,14 @@ macro_rules! async_publish_all {
 
 #[cfg(test)]
 mod tests {
-    use std::mem;
     use std::time::Duration;
 
     use serde::Serialize;
     use tokio::sync::mpsc::Sender;
     use tokio::time::{Instant, sleep};
 
+    use crate::bus::middleware::{HandlerFuture, NextHandler};
+    use crate::bus::subscription::SubscriptionGuard;
     use crate::event::{EventMetadata, EventWithMetadata};
     use crate::subscriber::SubscriberError;
 
@@ -170,28 +626,16 @@ mod tests {
         metadata: EventMetadata
     }
 
-    impl EventWithMetadata for TestEvent {
-        fn add_metadata(&mut self, key: String, value: String) {
-            self.metadata.add(key, value);
-        }
-
-        fn get_metadata(&self, key: &str) -> Option<&String> {
-            self.metadata.get(key)
-        }
-
-        fn metadata(&self) -> &EventMetadata {
-            &self.metadata
-        }
-
-        fn drain_metadata(&mut self) -> EventMetadata {
-            mem::take(&mut self.metadata)
-        }
-    }
+    crate::event_metadata!(TestEvent);
 
     impl Event for TestEvent {
         fn event_name(&self) -> &'static str {
             "test_event"
         }
+
+        fn event_metadata(&self) -> Option<&EventMetadata> {
+            Some(&self.metadata)
+        }
     }
 
     #[derive(Serialize)]
@@ -199,23 +643,7 @@ mod tests {
         metadata: EventMetadata
     }
 
-    impl EventWithMetadata for OtherTestEvent {
-        fn add_metadata(&mut self, key: String, value: String) {
-            self.metadata.add(key, value);
-        }
-
-        fn get_metadata(&self, key: &str) -> Option<&String> {
-            self.metadata.get(key)
-        }
-
-        fn metadata(&self) -> &EventMetadata {
-            &self.metadata
-        }
-
-        fn drain_metadata(&mut self) -> EventMetadata {
-            mem::take(&mut self.metadata)
-        }
-    }
+    crate::event_metadata!(OtherTestEvent);
 
 
     impl Event for OtherTestEvent {
@@ -248,7 +676,7 @@ mod tests {
 
     #[tokio::test]
     async fn it_should_publish_with_tokio_green_threads() {
-        let mut event_bus = TokioEventBus::default();
+        let event_bus = TokioEventBus::default();
         let (tx, mut rx) = tokio::sync::mpsc::channel(1);
 
         event_bus.register(Arc::new(TestEventHandler {
@@ -278,7 +706,7 @@ mod tests {
 
     #[tokio::test]
     async fn it_should_concurrently_do_all_events_with_sleep_of_one_second() {
-        let mut event_bus = TokioEventBus::default();
+        let event_bus = TokioEventBus::default();
 
         event_bus.register(Arc::new(SleepyEventHandler {
             sleep_time: Duration::from_secs(1)
@@ -294,4 +722,431 @@ mod tests {
 
         assert!(duration.as_secs() < 2);
     }
-}
\ No newline at end of file
+
+    struct Auditor {
+        sender: Sender<&'static str>
+    }
+
+    impl AsyncAnySubscriber for Auditor {
+        async fn handle_any(&self, event: &dyn Event) -> Result<(), SubscriberError> {
+            self.sender.send(event.event_name()).await.unwrap();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_deliver_matching_events_to_pattern_subscribers() {
+        let event_bus = TokioEventBus::default();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(2);
+
+        event_bus.register_pattern("other_*", Arc::new(Auditor { sender: tx }));
+
+        let _ = event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await;
+        let _ = event_bus.publish(OtherTestEvent { metadata: EventMetadata::default() }).await;
+
+        assert_eq!(rx.recv().await, Some("other_test_event"));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn it_should_unsubscribe_when_the_guard_is_dropped() {
+        let event_bus = Arc::new(TokioEventBus::default());
+        let (tx, mut rx) = tokio::sync::mpsc::channel(2);
+
+        let id = event_bus.register_any(Arc::new(Auditor { sender: tx }));
+        let guard = SubscriptionGuard::new(event_bus.clone(), id);
+
+        let _ = event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await;
+        drop(guard);
+        let _ = event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await;
+
+        assert_eq!(rx.recv().await, Some("test_event"));
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn it_should_register_subscribers_on_a_shared_bus() {
+        let event_bus = Arc::new(TokioEventBus::default());
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+
+        let bus = event_bus.clone();
+        tokio::spawn(async move {
+            bus.register(Arc::new(TestEventHandler { sender: tx }));
+        }).await.unwrap();
+
+        let _ = event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await;
+
+        assert_eq!(rx.recv().await, Some(1));
+    }
+
+    struct Named {
+        name: &'static str,
+        sleep_time: Duration,
+        sender: Sender<&'static str>
+    }
+
+    impl AsyncSubscriber<TestEvent> for Named {
+        async fn handle_event(&self, _event: &TestEvent) -> Result<(), SubscriberError> {
+            sleep(self.sleep_time).await;
+            self.sender.send(self.name).await.unwrap();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_run_subscribers_one_after_the_other_by_priority_when_sequential() {
+        let event_bus = TokioEventBus::default().with_handler_execution(HandlerExecution::Sequential);
+        let (tx, mut rx) = tokio::sync::mpsc::channel(2);
+
+        event_bus.register(Arc::new(Named { name: "notifier", sleep_time: Duration::ZERO, sender: tx.clone() }));
+        event_bus.register_with(
+            Arc::new(Named { name: "projection", sleep_time: Duration::from_millis(50), sender: tx }),
+            SubscriberOptions::default().with_priority(10)
+        );
+
+        let _ = event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await;
+
+        assert_eq!(rx.recv().await, Some("projection"));
+        assert_eq!(rx.recv().await, Some("notifier"));
+    }
+
+    struct TimedOutSubscribers {
+        sender: Sender<&'static str>
+    }
+
+    impl Middleware for TimedOutSubscribers {
+        fn after_handle(&self, _event: &dyn Event, subscriber: &'static str, result: &Result<(), SubscriberError>, _elapsed: Duration) {
+            if let Err(SubscriberError::Inner(e)) = result {
+                if e.is::<HandlerTimedOut>() {
+                    self.sender.try_send(subscriber).unwrap();
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_cancel_subscribers_taking_longer_than_their_timeout() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(2);
+        let mut event_bus = TokioEventBus::default().with_handler_timeout(Duration::from_millis(50));
+        event_bus.add_middleware(TimedOutSubscribers { sender: tx });
+
+        event_bus.register(Arc::new(SleepyEventHandler { sleep_time: Duration::from_secs(5) }));
+        event_bus.register_with(Arc::new(OtherEventHandler {}), SubscriberOptions::default().with_timeout(Duration::from_secs(1)));
+
+        let start = Instant::now();
+        let _ = event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await;
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(rx.recv().await.unwrap().ends_with("SleepyEventHandler"));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn it_should_return_a_handle_to_wait_for_a_detached_publish() {
+        let event_bus = TokioEventBus::default();
+        event_bus.register(Arc::new(SleepyEventHandler { sleep_time: Duration::from_millis(100) }));
+
+        let handle = event_bus.publish_detached(TestEvent { metadata: EventMetadata::default() });
+
+        assert!(!handle.is_finished());
+        handle.wait().await;
+    }
+
+    #[tokio::test]
+    async fn it_should_limit_the_subscribers_running_at_the_same_time() {
+        let event_bus = TokioEventBus::default().with_max_concurrent_handlers(1);
+
+        event_bus.register(Arc::new(SleepyEventHandler { sleep_time: Duration::from_millis(100) }));
+        event_bus.register(Arc::new(SleepyEventHandler { sleep_time: Duration::from_millis(100) }));
+
+        let start = Instant::now();
+        let _ = event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await;
+
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    struct GatedEventHandler {
+        gate: Arc<Semaphore>
+    }
+
+    impl AsyncSubscriber<TestEvent> for GatedEventHandler {
+        async fn handle_event(&self, _event: &TestEvent) -> Result<(), SubscriberError> {
+            let _ = self.gate.acquire().await.unwrap();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_fail_to_publish_when_the_queue_is_full() {
+        let gate = Arc::new(Semaphore::new(0));
+        let event_bus = TokioEventBus::default().with_bounded_queue(1, OverflowPolicy::Error);
+        event_bus.register(Arc::new(GatedEventHandler { gate: gate.clone() }));
+
+        assert!(event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await.is_ok());
+        sleep(Duration::from_millis(50)).await;
+
+        assert!(event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await.is_ok());
+        assert!(matches!(
+            event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await,
+            Err(PublishError::QueueFull)
+        ));
+
+        gate.add_permits(2);
+    }
+
+    struct PanickingEventHandler;
+
+    impl AsyncSubscriber<TestEvent> for PanickingEventHandler {
+        async fn handle_event(&self, _event: &TestEvent) -> Result<(), SubscriberError> {
+            panic!("Subscriber bug")
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_report_panicking_subscribers() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let event_bus = TokioEventBus::default().with_panic_handler(move |panicked| {
+            tx.send(panicked.clone()).unwrap();
+        });
+        event_bus.register(Arc::new(PanickingEventHandler));
+
+        let _ = event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await;
+
+        let panicked = rx.recv().await.unwrap();
+        assert_eq!(panicked.event_name, "test_event");
+        assert!(panicked.subscriber.ends_with("PanickingEventHandler"));
+        assert_eq!(panicked.message, "Subscriber bug");
+    }
+
+    struct OrderRecordingEventHandler {
+        handled: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl AsyncSubscriber<TestEvent> for OrderRecordingEventHandler {
+        async fn handle_event(&self, event: &TestEvent) -> Result<(), SubscriberError> {
+            let sequence = event.get_metadata("sequence").unwrap();
+            sleep(Duration::from_millis(40 - 10 * sequence.parse::<u64>().unwrap())).await;
+            self.handled.lock().unwrap().push(sequence.clone());
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_handle_the_events_of_a_key_in_order() {
+        let handled = Arc::new(Mutex::new(vec![]));
+        let event_bus = TokioEventBus::default().with_ordering_key(OrderingKey::Metadata("aggregate-id"));
+        event_bus.register(Arc::new(OrderRecordingEventHandler { handled: handled.clone() }));
+
+        let mut handles = vec![];
+        for sequence in 0..4 {
+            let mut event = TestEvent { metadata: EventMetadata::default() };
+            event.add_metadata("aggregate-id".to_string(), "1".to_string());
+            event.add_metadata("sequence".to_string(), sequence.to_string());
+            handles.push(event_bus.publish_detached(event));
+        }
+
+        for handle in handles {
+            handle.wait().await;
+        }
+
+        assert_eq!(*handled.lock().unwrap(), vec!["0", "1", "2", "3"]);
+    }
+
+    struct PanickingAfterFirstPublish;
+
+    impl Middleware for PanickingAfterFirstPublish {
+        fn after_publish(&self, event: &dyn Event, _elapsed: Duration) {
+            if event.event_metadata().and_then(|metadata| metadata.get("sequence")).is_some_and(|sequence| sequence == "0") {
+                panic!("Middleware panicked");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_handling_the_events_of_a_key_after_a_dispatch_panicked() {
+        let handled = Arc::new(Mutex::new(vec![]));
+        let mut event_bus = TokioEventBus::default().with_ordering_key(OrderingKey::Metadata("aggregate-id"));
+        event_bus.add_middleware(PanickingAfterFirstPublish);
+        event_bus.register(Arc::new(OrderRecordingEventHandler { handled: handled.clone() }));
+
+        for sequence in 0..2 {
+            let mut event = TestEvent { metadata: EventMetadata::default() };
+            event.add_metadata("aggregate-id".to_string(), "1".to_string());
+            event.add_metadata("sequence".to_string(), sequence.to_string());
+
+            let handle = event_bus.publish_detached(event);
+            assert!(tokio::time::timeout(Duration::from_secs(1), handle.wait()).await.is_ok());
+        }
+
+        assert_eq!(*handled.lock().unwrap(), vec!["0", "1"]);
+    }
+
+    struct FlakyEventHandler {
+        attempts: Arc<Mutex<u32>>,
+        failures: u32,
+    }
+
+    impl AsyncSubscriber<TestEvent> for FlakyEventHandler {
+        async fn handle_event(&self, _event: &TestEvent) -> Result<(), SubscriberError> {
+            let mut attempts = self.attempts.lock().unwrap();
+            *attempts += 1;
+
+            if *attempts <= self.failures {
+                return Err(SubscriberError::Inner("Temporary failure".into()));
+            }
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_retry_failing_subscribers() {
+        let attempts = Arc::new(Mutex::new(0));
+        let dead_letters = Arc::new(DeadLetterSink::new());
+        let event_bus = TokioEventBus::default()
+            .with_retry_policy(RetryPolicy::new(3).with_backoff(Duration::from_millis(1), Duration::from_millis(10)))
+            .with_dead_letter_sink(dead_letters.clone());
+        event_bus.register(Arc::new(FlakyEventHandler { attempts: attempts.clone(), failures: 2 }));
+
+        let _ = event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await;
+
+        assert_eq!(*attempts.lock().unwrap(), 3);
+        assert!(dead_letters.is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_should_dead_letter_events_once_out_of_attempts() {
+        let attempts = Arc::new(Mutex::new(0));
+        let dead_letters = Arc::new(DeadLetterSink::new());
+        let event_bus = TokioEventBus::default()
+            .with_retry_policy(RetryPolicy::new(2).with_backoff(Duration::from_millis(1), Duration::from_millis(10)))
+            .with_dead_letter_sink(dead_letters.clone());
+        event_bus.register(Arc::new(FlakyEventHandler { attempts: attempts.clone(), failures: 5 }));
+
+        let _ = event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await;
+
+        let dead_letters = dead_letters.drain();
+        assert_eq!(*attempts.lock().unwrap(), 2);
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].attempts, 2);
+        assert!(dead_letters[0].subscriber.ends_with("FlakyEventHandler"));
+        assert!(downcast_ref!(dead_letters[0].event.as_ref(), TestEvent).is_some());
+    }
+
+    struct RequeuingEventHandler {
+        attempts: Arc<Mutex<u32>>,
+    }
+
+    impl AsyncSubscriber<TestEvent> for RequeuingEventHandler {
+        async fn handle_event(&self, _event: &TestEvent) -> Result<(), SubscriberError> {
+            *self.attempts.lock().unwrap() += 1;
+
+            Err(SubscriberError::Requeue)
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_stop_requeuing_a_subscriber_once_out_of_requeues() {
+        let attempts = Arc::new(Mutex::new(0));
+        let dead_letters = Arc::new(DeadLetterSink::new());
+        let event_bus = TokioEventBus::default()
+            .with_retry_policy(RetryPolicy::new(3).with_max_requeues(2).with_backoff(Duration::from_millis(1), Duration::from_millis(10)))
+            .with_dead_letter_sink(dead_letters.clone());
+        event_bus.register(Arc::new(RequeuingEventHandler { attempts: attempts.clone() }));
+
+        let _ = event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await;
+
+        assert_eq!(*attempts.lock().unwrap(), 3);
+        assert!(dead_letters.is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_should_dead_letter_panicking_subscribers_without_retrying_them() {
+        let dead_letters = Arc::new(DeadLetterSink::new());
+        let event_bus = TokioEventBus::default()
+            .with_retry_policy(RetryPolicy::new(3).with_backoff(Duration::from_millis(1), Duration::from_millis(10)))
+            .with_dead_letter_sink(dead_letters.clone());
+        event_bus.register(Arc::new(PanickingEventHandler));
+
+        let _ = event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await;
+
+        let dead_letters = dead_letters.drain();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].attempts, 1);
+        assert!(dead_letters[0].error.ends_with("Subscriber bug"));
+    }
+
+    #[tokio::test]
+    async fn it_should_release_the_permits_of_a_subscriber_waiting_to_be_retried() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let event_bus = TokioEventBus::default()
+            .with_max_concurrent_handlers(1)
+            .with_retry_policy(RetryPolicy::new(2).with_backoff(Duration::from_millis(500), Duration::from_millis(500)));
+        event_bus.register(Arc::new(FlakyEventHandler { attempts: Arc::new(Mutex::new(0)), failures: 1 }));
+        event_bus.register(Arc::new(Named { name: "notifier", sleep_time: Duration::ZERO, sender: tx }));
+
+        let handle = event_bus.publish_detached(TestEvent { metadata: EventMetadata::default() });
+
+        assert_eq!(tokio::time::timeout(Duration::from_millis(250), rx.recv()).await.unwrap(), Some("notifier"));
+        handle.wait().await;
+    }
+
+    #[tokio::test]
+    async fn it_should_not_hold_a_permit_of_the_bus_while_waiting_for_a_busy_subscriber() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let event_bus = TokioEventBus::default().with_max_concurrent_handlers(2);
+        event_bus.register_with(
+            Arc::new(SleepyEventHandler { sleep_time: Duration::from_millis(500) }),
+            SubscriberOptions::default().with_max_concurrency(1)
+        );
+        event_bus.register_pattern("other_*", Arc::new(Auditor { sender: tx }));
+
+        let first = event_bus.publish_detached(TestEvent { metadata: EventMetadata::default() });
+        let second = event_bus.publish_detached(TestEvent { metadata: EventMetadata::default() });
+        sleep(Duration::from_millis(50)).await;
+        let _ = event_bus.publish_detached(OtherTestEvent { metadata: EventMetadata::default() });
+
+        assert_eq!(tokio::time::timeout(Duration::from_millis(250), rx.recv()).await.unwrap(), Some("other_test_event"));
+        first.wait().await;
+        second.wait().await;
+    }
+
+    struct TenantMiddleware;
+
+    impl Middleware for TenantMiddleware {
+        fn around_handle_async(&self, event: Arc<dyn Event>, _subscriber: &'static str, next: NextHandler) -> HandlerFuture {
+            let Some(test_event) = downcast_ref!(event.as_ref(), TestEvent) else {
+                return next(event);
+            };
+
+            let mut metadata = test_event.metadata.clone();
+            metadata.add("tenant".to_string(), "acme".to_string());
+
+            next(Arc::new(TestEvent { metadata }))
+        }
+    }
+
+    struct TenantRecorder {
+        sender: Sender<Option<String>>
+    }
+
+    impl AsyncSubscriber<TestEvent> for TenantRecorder {
+        async fn handle_event(&self, event: &TestEvent) -> Result<(), SubscriberError> {
+            self.sender.send(event.get_metadata("tenant").cloned()).await.unwrap();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_hand_the_subscribers_the_event_passed_by_the_around_hooks() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let mut event_bus = TokioEventBus::default();
+        event_bus.add_middleware(TenantMiddleware);
+        event_bus.register(Arc::new(TenantRecorder { sender: tx }));
+
+        let _ = event_bus.publish(TestEvent { metadata: EventMetadata::default() }).await;
+
+        assert_eq!(rx.recv().await, Some(Some("acme".to_string())));
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::bus::error::PublishError;
+
+pub(crate) type DispatchFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+///
+/// What publishing does when the queue of a bus is full.
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    ///
+    /// Wait until an event leaves the queue.
+    ///
+    #[default]
+    Wait,
+    ///
+    /// Discard the oldest queued event to make room.
+    ///
+    DropOldest,
+    ///
+    /// Fail with `PublishError::QueueFull`.
+    ///
+    Error,
+}
+
+///
+/// A bounded queue of dispatches handled one after the other by a background task.
+///
+pub(crate) struct DispatchQueue {
+    capacity: usize,
+    overflow_policy: OverflowPolicy,
+    pending: Mutex<VecDeque<DispatchFuture>>,
+    available: Notify,
+    freed: Notify,
+    worker: OnceLock<JoinHandle<()>>,
+}
+
+impl DispatchQueue {
+    pub(crate) fn new(capacity: usize, overflow_policy: OverflowPolicy) -> Self {
+        DispatchQueue {
+            capacity: capacity.max(1),
+            overflow_policy,
+            pending: Mutex::new(VecDeque::new()),
+            available: Notify::new(),
+            freed: Notify::new(),
+            worker: OnceLock::new(),
+        }
+    }
+
+    pub(crate) async fn push(self: &Arc<Self>, dispatch: DispatchFuture) -> Result<(), PublishError> {
+        self.worker.get_or_init(|| tokio::spawn(self.clone().run()));
+
+        loop {
+            {
+                let mut pending = self.pending.lock().unwrap();
+
+                if pending.len() < self.capacity {
+                    pending.push_back(dispatch);
+                    self.available.notify_one();
+                    return Ok(());
+                }
+
+                match self.overflow_policy {
+                    OverflowPolicy::Error => return Err(PublishError::QueueFull),
+                    OverflowPolicy::DropOldest => {
+                        log::warn!("Dropping the oldest queued event, the queue is full");
+                        pending.pop_front();
+                        pending.push_back(dispatch);
+                        self.available.notify_one();
+                        return Ok(());
+                    },
+                    OverflowPolicy::Wait => {},
+                }
+            }
+
+            self.freed.notified().await;
+        }
+    }
+
+    ///
+    /// Stop handling queued dispatches, dropping the pending ones.
+    ///
+    pub(crate) fn stop(&self) {
+        if let Some(worker) = self.worker.get() {
+            worker.abort();
+        }
+    }
+
+    async fn run(self: Arc<Self>) {
+        loop {
+            let next = self.pending.lock().unwrap().pop_front();
+
+            match next {
+                Some(dispatch) => {
+                    self.freed.notify_one();
+                    dispatch.await;
+                },
+                None => self.available.notified().await,
+            }
+        }
+    }
+}
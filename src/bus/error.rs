@@ -3,15 +3,30 @@ use std::fmt::{Display, Formatter};
 
 #[derive(Debug)]
 pub enum PublishError {
-    CannotSerializeEvent,
-    CannotOpenChannel,
-    CannotPublishEvent,
+    CannotSerializeEvent(Box<dyn Error + Send + Sync>),
+    CannotOpenChannel(Box<dyn Error + Send + Sync>),
+    CannotPublishEvent(Box<dyn Error + Send + Sync>),
+    QueueFull,
 }
 
 impl Display for PublishError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Error publishing")
+        match self {
+            PublishError::CannotSerializeEvent(error) => write!(f, "Cannot serialize event: {}", error),
+            PublishError::CannotOpenChannel(error) => write!(f, "Cannot open channel: {}", error),
+            PublishError::CannotPublishEvent(error) => write!(f, "Cannot publish event: {}", error),
+            PublishError::QueueFull => write!(f, "The queue of the bus is full"),
+        }
     }
 }
 
-impl Error for PublishError {}
\ No newline at end of file
+impl Error for PublishError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PublishError::CannotSerializeEvent(error)
+            | PublishError::CannotOpenChannel(error)
+            | PublishError::CannotPublishEvent(error) => Some(error.as_ref()),
+            PublishError::QueueFull => None,
+        }
+    }
+}
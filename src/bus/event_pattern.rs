@@ -0,0 +1,68 @@
+///
+/// A glob over event names where `*` matches any sequence of characters, e.g. `chat.*`.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventPattern(String);
+
+impl EventPattern {
+    pub fn new(pattern: &str) -> Self {
+        EventPattern(pattern.to_string())
+    }
+
+    ///
+    /// A pattern matching every event.
+    ///
+    pub fn any() -> Self {
+        EventPattern("*".to_string())
+    }
+
+    pub fn matches(&self, event_name: &str) -> bool {
+        let pattern = self.0.as_bytes();
+        let name = event_name.as_bytes();
+
+        let (mut p, mut n) = (0, 0);
+        let mut backtrack: Option<(usize, usize)> = None;
+
+        while n < name.len() {
+            if p < pattern.len() && pattern[p] == b'*' {
+                backtrack = Some((p, n));
+                p += 1;
+            } else if p < pattern.len() && pattern[p] == name[n] {
+                p += 1;
+                n += 1;
+            } else if let Some((star, matched)) = backtrack {
+                p = star + 1;
+                n = matched + 1;
+                backtrack = Some((star, matched + 1));
+            } else {
+                return false;
+            }
+        }
+
+        pattern[p..].iter().all(|c| *c == b'*')
+    }
+}
+
+impl From<&str> for EventPattern {
+    fn from(pattern: &str) -> Self {
+        EventPattern::new(pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_match_event_names_with_wildcards() {
+        assert!(EventPattern::new("chat.*").matches("chat.message_sent"));
+        assert!(EventPattern::new("*.sent").matches("chat.message.sent"));
+        assert!(EventPattern::new("chat.*.sent").matches("chat.message.sent"));
+        assert!(EventPattern::new("chat_message_sent").matches("chat_message_sent"));
+        assert!(EventPattern::any().matches("anything"));
+
+        assert!(!EventPattern::new("chat.*").matches("user.created"));
+        assert!(!EventPattern::new("chat.*.sent").matches("chat.message.received"));
+        assert!(!EventPattern::new("chat").matches("chat.message_sent"));
+    }
+}
@@ -0,0 +1,48 @@
+use std::any::Any;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+///
+/// A subscriber that panicked while handling an event.
+///
+#[derive(Debug, Clone)]
+pub struct HandlerPanicked {
+    pub event_name: &'static str,
+    pub subscriber: &'static str,
+    pub message: String,
+}
+
+impl HandlerPanicked {
+    pub(crate) fn new(event_name: &'static str, subscriber: &'static str, payload: &(dyn Any + Send)) -> Self {
+        let message = payload.downcast_ref::<&str>()
+                             .map(|message| message.to_string())
+                             .or_else(|| payload.downcast_ref::<String>().cloned())
+                             .unwrap_or_else(|| "Unknown panic".to_string());
+
+        HandlerPanicked {
+            event_name,
+            subscriber,
+            message,
+        }
+    }
+}
+
+impl Display for HandlerPanicked {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} panicked while handling {}: {}", self.subscriber, self.event_name, self.message)
+    }
+}
+
+impl Error for HandlerPanicked {}
+
+///
+/// Called with every panic caught by a bus.
+///
+pub(crate) type PanicHandler = Arc<dyn Fn(&HandlerPanicked) + Send + Sync>;
+
+pub(crate) fn report_panic(panic_handler: Option<&PanicHandler>, panicked: &HandlerPanicked) {
+    if let Some(panic_handler) = panic_handler {
+        panic_handler(panicked);
+    }
+}
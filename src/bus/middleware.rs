@@ -0,0 +1,390 @@
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::event::{CORRELATION_ID, Event, EventMetadata, EventWithMetadata, unique_id};
+use crate::subscriber::SubscriberError;
+
+///
+/// The metadata key holding the moment an event was published, in milliseconds since the unix epoch.
+///
+pub const PUBLISHED_AT: &str = "published-at";
+
+///
+/// The future of an asynchronous subscriber handling an event.
+///
+#[cfg(feature = "async")]
+pub type HandlerFuture = Pin<Box<dyn Future<Output = Result<(), SubscriberError>> + Send>>;
+
+///
+/// The rest of the asynchronous handler chain: the next middlewares and then the subscriber.
+///
+#[cfg(feature = "async")]
+pub type NextHandler = Box<dyn FnOnce(Arc<dyn Event>) -> HandlerFuture + Send>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MiddlewareAction {
+    Continue,
+    Stop,
+}
+
+///
+/// Hooks invoked by the event buses around publishing and around every subscriber.
+///
+/// Returning `MiddlewareAction::Stop` from `before_publish` drops the event, and from
+/// `before_handle` skips that subscriber.
+///
+/// `around_handle` and `around_handle_async` wrap every subscriber of the synchronous and
+/// multithreading buses and of the Tokio bus. They call `next` with the event the subscriber
+/// receives, which can be another one than the published event, e.g. a copy with more metadata,
+/// or return without calling it to short-circuit the subscriber.
+///
+pub trait Middleware: Send + Sync + 'static {
+    ///
+    /// Stamp the metadata of a published event, before `before_publish`. The metadata is `None` when
+    /// the event exposes it neither through `Event::metadata_mut` nor through `EventWithMetadata`.
+    ///
+    fn stamp_metadata(&self, _event_name: &'static str, _metadata: Option<&mut EventMetadata>) {}
+
+    fn before_publish(&self, _event: &mut dyn Event) -> MiddlewareAction {
+        MiddlewareAction::Continue
+    }
+
+    fn after_publish(&self, _event: &dyn Event, _elapsed: Duration) {}
+
+    ///
+    /// The subscribers of an event share it, so a middleware changes the event a subscriber
+    /// receives in `around_handle` instead.
+    ///
+    fn before_handle(&self, _event: &dyn Event, _subscriber: &'static str) -> MiddlewareAction {
+        MiddlewareAction::Continue
+    }
+
+    fn around_handle(
+        &self,
+        event: &dyn Event,
+        _subscriber: &'static str,
+        next: &dyn Fn(&dyn Event) -> Result<(), SubscriberError>
+    ) -> Result<(), SubscriberError> {
+        next(event)
+    }
+
+    #[cfg(feature = "async")]
+    fn around_handle_async(&self, event: Arc<dyn Event>, _subscriber: &'static str, next: NextHandler) -> HandlerFuture {
+        next(event)
+    }
+
+    fn after_handle(&self, _event: &dyn Event, _subscriber: &'static str, _result: &Result<(), SubscriberError>, _elapsed: Duration) {}
+}
+
+///
+/// Middlewares in the order they were added.
+///
+/// `before` hooks run in that order and `after` hooks in the reverse one.
+///
+#[derive(Default, Clone)]
+pub struct MiddlewareStack {
+    middlewares: Vec<Arc<dyn Middleware>>
+}
+
+impl MiddlewareStack {
+    pub fn push<M: Middleware>(&mut self, middleware: M) {
+        self.middlewares.push(Arc::new(middleware));
+    }
+
+    pub fn before_publish(&self, event: &mut dyn Event) -> MiddlewareAction {
+        let event_name = event.event_name();
+        self.stamp_metadata(event_name, event.metadata_mut());
+
+        self.run_before_publish(event)
+    }
+
+    ///
+    /// Same as `before_publish`, stamping the metadata through `EventWithMetadata` instead of `Event::metadata_mut`.
+    ///
+    pub fn before_publish_with_metadata<E: Event + EventWithMetadata>(&self, event: &mut E) -> MiddlewareAction {
+        let mut metadata = event.drain_metadata();
+        self.stamp_metadata(event.event_name(), Some(&mut metadata));
+        for (key, value) in metadata.iter() {
+            event.add_metadata(key.clone(), value.clone());
+        }
+
+        self.run_before_publish(event)
+    }
+
+    fn stamp_metadata(&self, event_name: &'static str, mut metadata: Option<&mut EventMetadata>) {
+        for middleware in &self.middlewares {
+            middleware.stamp_metadata(event_name, metadata.as_deref_mut());
+        }
+    }
+
+    fn run_before_publish(&self, event: &mut dyn Event) -> MiddlewareAction {
+        for middleware in &self.middlewares {
+            if middleware.before_publish(event) == MiddlewareAction::Stop {
+                return MiddlewareAction::Stop;
+            }
+        }
+
+        MiddlewareAction::Continue
+    }
+
+    pub fn after_publish(&self, event: &dyn Event, elapsed: Duration) {
+        for middleware in self.middlewares.iter().rev() {
+            middleware.after_publish(event, elapsed);
+        }
+    }
+
+    pub fn before_handle(&self, event: &dyn Event, subscriber: &'static str) -> MiddlewareAction {
+        for middleware in &self.middlewares {
+            if middleware.before_handle(event, subscriber) == MiddlewareAction::Stop {
+                return MiddlewareAction::Stop;
+            }
+        }
+
+        MiddlewareAction::Continue
+    }
+
+    pub fn after_handle(&self, event: &dyn Event, subscriber: &'static str, result: &Result<(), SubscriberError>, elapsed: Duration) {
+        for middleware in self.middlewares.iter().rev() {
+            middleware.after_handle(event, subscriber, result, elapsed);
+        }
+    }
+
+    ///
+    /// Run the handler of the subscriber inside the `around_handle` hooks, the first middleware outermost.
+    ///
+    pub fn around_handle(
+        &self,
+        event: &dyn Event,
+        subscriber: &'static str,
+        handler: &dyn Fn(&dyn Event) -> Result<(), SubscriberError>
+    ) -> Result<(), SubscriberError> {
+        self.around_handle_from(0, event, subscriber, handler)
+    }
+
+    fn around_handle_from(
+        &self,
+        index: usize,
+        event: &dyn Event,
+        subscriber: &'static str,
+        handler: &dyn Fn(&dyn Event) -> Result<(), SubscriberError>
+    ) -> Result<(), SubscriberError> {
+        match self.middlewares.get(index) {
+            Some(middleware) => middleware.around_handle(event, subscriber, &|event| self.around_handle_from(index + 1, event, subscriber, handler)),
+            None => handler(event),
+        }
+    }
+
+    ///
+    /// Run the handler of the subscriber inside the `around_handle_async` hooks, the first middleware outermost.
+    ///
+    #[cfg(feature = "async")]
+    pub fn around_handle_async(&self, event: Arc<dyn Event>, subscriber: &'static str, handler: NextHandler) -> HandlerFuture {
+        let next = self.middlewares
+                       .iter()
+                       .rev()
+                       .fold(handler, |next, middleware| {
+                           let middleware = middleware.clone();
+                           Box::new(move |event| middleware.around_handle_async(event, subscriber, next))
+                       });
+
+        next(event)
+    }
+}
+
+///
+/// Logs every published event and the outcome of every subscriber.
+///
+pub struct LoggingMiddleware;
+
+impl Middleware for LoggingMiddleware {
+    fn before_publish(&self, event: &mut dyn Event) -> MiddlewareAction {
+        log::debug!("Publishing event {}", event.event_name());
+
+        MiddlewareAction::Continue
+    }
+
+    fn after_handle(&self, event: &dyn Event, subscriber: &'static str, result: &Result<(), SubscriberError>, _elapsed: Duration) {
+        match result {
+            Ok(_) => log::debug!("Event {} handled by {}", event.event_name(), subscriber),
+            Err(e) => log::warn!("Event {} failed in {}: {}", event.event_name(), subscriber, e),
+        }
+    }
+}
+
+///
+/// Stamps a correlation id, unless the event already has one, and the publishing timestamp.
+///
+/// Events exposing their metadata neither through `Event::metadata_mut` nor through `EventWithMetadata`
+/// cannot be stamped, and a warning is logged for each of them.
+///
+pub struct MetadataStampingMiddleware {
+    generate_correlation_id: fn() -> String,
+}
+
+impl MetadataStampingMiddleware {
+    pub fn new() -> Self {
+        MetadataStampingMiddleware {
+            generate_correlation_id: unique_id,
+        }
+    }
+
+    pub fn with_correlation_id_generator(generate_correlation_id: fn() -> String) -> Self {
+        MetadataStampingMiddleware {
+            generate_correlation_id,
+        }
+    }
+}
+
+impl Default for MetadataStampingMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for MetadataStampingMiddleware {
+    fn stamp_metadata(&self, event_name: &'static str, metadata: Option<&mut EventMetadata>) {
+        let Some(metadata) = metadata else {
+            log::warn!("Event {} does not expose its metadata, it is published without being stamped", event_name);
+            return;
+        };
+
+        if metadata.get(CORRELATION_ID).is_none() {
+            metadata.add(CORRELATION_ID.to_string(), (self.generate_correlation_id)());
+        }
+
+        metadata.add(PUBLISHED_AT.to_string(), unix_millis().to_string());
+    }
+}
+
+fn unix_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default()
+}
+
+///
+/// Logs how long every subscriber and every publish take.
+///
+pub struct TimingMiddleware;
+
+impl Middleware for TimingMiddleware {
+    fn after_publish(&self, event: &dyn Event, elapsed: Duration) {
+        log::debug!("Event {} published in {:?}", event.event_name(), elapsed);
+    }
+
+    fn after_handle(&self, event: &dyn Event, subscriber: &'static str, _result: &Result<(), SubscriberError>, elapsed: Duration) {
+        log::debug!("Event {} handled by {} in {:?}", event.event_name(), subscriber, elapsed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StampedEvent {
+        metadata: EventMetadata
+    }
+
+    impl Event for StampedEvent {
+        fn event_name(&self) -> &'static str {
+            "stamped_event"
+        }
+
+        fn metadata_mut(&mut self) -> Option<&mut EventMetadata> {
+            Some(&mut self.metadata)
+        }
+    }
+
+    #[test]
+    fn it_should_stamp_metadata_keeping_an_existing_correlation_id() {
+        let mut stack = MiddlewareStack::default();
+        stack.push(MetadataStampingMiddleware::with_correlation_id_generator(|| "generated".to_string()));
+
+        let mut event = StampedEvent { metadata: EventMetadata::default() };
+        let mut correlated_event = StampedEvent { metadata: EventMetadata::default() };
+        correlated_event.metadata.add(CORRELATION_ID.to_string(), "existing".to_string());
+
+        assert_eq!(stack.before_publish(&mut event), MiddlewareAction::Continue);
+        stack.before_publish(&mut correlated_event);
+
+        assert_eq!(event.metadata.get(CORRELATION_ID), Some(&"generated".to_string()));
+        assert!(event.metadata.get(PUBLISHED_AT).is_some());
+        assert_eq!(correlated_event.metadata.get(CORRELATION_ID), Some(&"existing".to_string()));
+    }
+
+    struct HandWrittenEvent {
+        metadata: EventMetadata
+    }
+
+    impl Event for HandWrittenEvent {
+        fn event_name(&self) -> &'static str {
+            "hand_written_event"
+        }
+    }
+
+    crate::event_metadata!(HandWrittenEvent);
+
+    #[test]
+    fn it_should_stamp_metadata_through_event_with_metadata() {
+        let mut stack = MiddlewareStack::default();
+        stack.push(MetadataStampingMiddleware::with_correlation_id_generator(|| "generated".to_string()));
+
+        let mut event = HandWrittenEvent { metadata: EventMetadata::default() };
+        event.metadata.add("tenant".to_string(), "acme".to_string());
+
+        assert_eq!(stack.before_publish_with_metadata(&mut event), MiddlewareAction::Continue);
+
+        assert_eq!(event.get_metadata(CORRELATION_ID), Some(&"generated".to_string()));
+        assert_eq!(event.get_metadata("tenant"), Some(&"acme".to_string()));
+        assert!(event.get_metadata(PUBLISHED_AT).is_some());
+    }
+
+    struct TenantMiddleware;
+
+    impl Middleware for TenantMiddleware {
+        fn around_handle(
+            &self,
+            event: &dyn Event,
+            subscriber: &'static str,
+            next: &dyn Fn(&dyn Event) -> Result<(), SubscriberError>
+        ) -> Result<(), SubscriberError> {
+            if subscriber == "skipped" {
+                return Ok(());
+            }
+
+            let Some(event) = downcaster::downcast_ref!(event, HandWrittenEvent) else {
+                return next(event);
+            };
+
+            let mut metadata = event.metadata.clone();
+            metadata.add("tenant".to_string(), "acme".to_string());
+
+            next(&HandWrittenEvent { metadata })
+        }
+    }
+
+    #[test]
+    fn it_should_run_the_handlers_around_the_middlewares_with_the_event_they_pass() {
+        let mut stack = MiddlewareStack::default();
+        stack.push(TenantMiddleware);
+
+        let event = HandWrittenEvent { metadata: EventMetadata::default() };
+        let tenants = std::cell::RefCell::new(vec![]);
+        let handler = |event: &dyn Event| {
+            let event = downcaster::downcast_ref!(event, HandWrittenEvent).unwrap();
+            tenants.borrow_mut().push(event.get_metadata("tenant").cloned());
+            Ok(())
+        };
+
+        stack.around_handle(&event, "handled", &handler).unwrap();
+        stack.around_handle(&event, "skipped", &handler).unwrap();
+
+        assert_eq!(*tenants.borrow(), vec![Some("acme".to_string())]);
+        assert!(event.get_metadata("tenant").is_none());
+    }
+}
@@ -1,4 +1,3 @@
-#[cfg(feature = "rabbit")]
 #[cfg(feature = "async")]
 use serde::Serialize;
 
@@ -6,25 +5,38 @@ use crate::bus::error::PublishError;
 use crate::event::{Event, EventWithMetadata};
 
 pub mod synchronous_bus;
+pub mod publish_report;
+pub mod middleware;
+pub mod event_pattern;
+pub mod subscription;
+pub mod failure;
+pub mod retry;
 
 #[cfg(feature = "async")]
 pub mod asynchronous_bus;
+#[cfg(feature = "async")]
+pub mod dispatch_queue;
 
 #[cfg(feature = "multithreading")]
 pub mod multithreading_bus;
 
+#[cfg(any(feature = "async", feature = "multithreading"))]
+pub mod ordering;
+
 #[cfg(feature = "rabbit")]
 pub mod rabbitmq_bus;
-pub(crate) mod error;
+
+#[cfg(feature = "postgres")]
+pub mod postgres_bus;
+pub mod error;
 
 pub trait EventBus {
     fn publish<E: Event>(&self, event: E);
 }
 
 #[cfg(feature = "async")]
-#[allow(async_fn_in_trait)]
 pub trait AsynchronousEventBus {
-    async fn publish<E: Event + EventWithMetadata + Serialize>(&self, event: E) -> Result<(), PublishError>;
+    fn publish<E: Event + EventWithMetadata + Serialize>(&self, event: E) -> impl std::future::Future<Output = Result<(), PublishError>> + Send;
 }
 
 #[macro_export]
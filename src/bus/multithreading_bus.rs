@@ -1,13 +1,19 @@
-use std::any::TypeId;
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::any::{Any, type_name, TypeId};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
 use downcaster::{Downcast, downcast_ref};
 use rayon::{ThreadPool, ThreadPoolBuilder};
 
 use crate::bus::EventBus;
+use crate::bus::event_pattern::EventPattern;
+use crate::bus::failure::{HandlerPanicked, PanicHandler, report_panic};
+use crate::bus::middleware::{Middleware, MiddlewareAction, MiddlewareStack};
+use crate::bus::ordering::{LaneGuard, Lanes, OrderingKey};
+use crate::bus::subscription::{HandlerExecution, SubscriberOptions, SubscriberRegistry, SubscriptionId, Unsubscribe};
 use crate::event::Event;
-use crate::subscriber::Subscriber;
+use crate::subscriber::{AnySubscriber, Subscriber, SubscriberError};
 
 #[derive(Debug)]
 pub enum MultithreadingEventBusError {
@@ -25,22 +31,137 @@ impl std::fmt::Display for MultithreadingEventBusError {
 impl std::error::Error for MultithreadingEventBusError {}
 
 ///
-/// A closure that takes an event and returns nothing.
+/// A closure that takes an event and returns the result of the subscriber.
 ///
-type SubscriberClosure = Arc<dyn Fn(&dyn Event) + Send + Sync>;
+type SubscriberClosure = Arc<dyn Fn(&dyn Event) -> Result<(), SubscriberError> + Send + Sync>;
+
+struct Registration {
+    subscriber: &'static str,
+    handler: SubscriberClosure,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+///
+/// Counts the handlers that are still running and wakes up whoever waits for them to finish.
+///
+#[derive(Default)]
+struct PendingHandlers {
+    count: Mutex<usize>,
+    finished: Condvar,
+}
+
+impl PendingHandlers {
+    fn add(&self, handlers: usize) {
+        *self.count.lock().unwrap() += handlers;
+    }
+
+    fn done(&self) {
+        let mut count = self.count.lock().unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.finished.notify_all();
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        *self.count.lock().unwrap() == 0
+    }
+
+    fn wait(&self) {
+        let count = self.count.lock().unwrap();
+        let _count = self.finished.wait_while(count, |count| *count > 0).unwrap();
+    }
+
+    fn wait_timeout(&self, timeout: Duration) -> bool {
+        let count = self.count.lock().unwrap();
+        let (_count, result) = self.finished.wait_timeout_while(count, timeout, |count| *count > 0).unwrap();
+        !result.timed_out()
+    }
+}
+
+///
+/// Marks a handler as finished once its job is done, even if it unwinds.
+///
+struct HandlerDone(Vec<Arc<PendingHandlers>>);
+
+impl Drop for HandlerDone {
+    fn drop(&mut self) {
+        self.0.iter().for_each(|pending| pending.done());
+    }
+}
+
+///
+/// Runs the `after_publish` hook of the middlewares once the last job of an event drops it.
+///
+struct PublishDone {
+    middlewares: Arc<MiddlewareStack>,
+    event: Arc<dyn Event>,
+    start: Instant,
+}
+
+impl Drop for PublishDone {
+    fn drop(&mut self) {
+        let after_publish = catch_unwind(AssertUnwindSafe(|| self.middlewares.after_publish(self.event.as_ref(), self.start.elapsed())));
+
+        if after_publish.is_err() {
+            log::error!("A middleware panicked after publishing {}", self.event.event_name());
+        }
+    }
+}
+
+///
+/// Handle to the subscribers of a published event, finished once all of them have run.
+///
+#[derive(Clone)]
+pub struct CompletionHandle {
+    pending: Arc<PendingHandlers>,
+}
+
+impl CompletionHandle {
+    pub fn is_finished(&self) -> bool {
+        self.pending.is_finished()
+    }
+
+    ///
+    /// Block until all subscribers of the event have run.
+    ///
+    pub fn wait(&self) {
+        self.pending.wait()
+    }
+
+    ///
+    /// Block until all subscribers of the event have run, returning false if the timeout elapses first.
+    ///
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        self.pending.wait_timeout(timeout)
+    }
+}
 
 ///
 /// A multithreading event bus that uses a thread pool to handle events.
 ///
 pub struct MultithreadingEventBus {
-    subscribers: HashMap<TypeId, Vec<SubscriberClosure>>,
+    subscribers: RwLock<SubscriberRegistry<Arc<Registration>>>,
+    middlewares: Arc<MiddlewareStack>,
+    handler_execution: HandlerExecution,
+    panic_handler: Option<PanicHandler>,
+    ordering_key: OrderingKey,
+    lanes: Arc<Lanes<Vec<Job>>>,
+    pending: Arc<PendingHandlers>,
     thread_pool: ThreadPool,
 }
 
 impl Default for MultithreadingEventBus {
     fn default() -> Self {
         Self {
-            subscribers: HashMap::default(),
+            subscribers: RwLock::default(),
+            middlewares: Arc::default(),
+            handler_execution: HandlerExecution::default(),
+            panic_handler: None,
+            ordering_key: OrderingKey::default(),
+            lanes: Arc::default(),
+            pending: Arc::default(),
             thread_pool: ThreadPoolBuilder::new().build().expect("Error creating thread pool"),
         }
     }
@@ -73,48 +194,233 @@ impl MultithreadingEventBus {
         Ok(Self::new(thread_pool))
     }
 
+    ///
+    /// Run the subscribers of an event concurrently, or one after the other in a single pool thread.
+    ///
+    pub fn with_handler_execution(mut self, handler_execution: HandlerExecution) -> Self {
+        self.handler_execution = handler_execution;
+        self
+    }
+
+    ///
+    /// Call `panic_handler` with every subscriber panic, which is caught and reported to the
+    /// middlewares as an error instead of unwinding the pool thread. The panics of the
+    /// `before_handle` and `after_handle` hooks are caught and reported too.
+    ///
+    pub fn with_panic_handler(mut self, panic_handler: impl Fn(&HandlerPanicked) + Send + Sync + 'static) -> Self {
+        self.panic_handler = Some(Arc::new(panic_handler));
+        self
+    }
+
+    ///
+    /// Take the ordering key of the events from somewhere else than `Event::ordering_key`.
+    ///
+    /// The subscribers of an event with a key only start once the ones of the previous event with
+    /// the same key are done.
+    ///
+    pub fn with_ordering_key(mut self, ordering_key: OrderingKey) -> Self {
+        self.ordering_key = ordering_key;
+        self
+    }
+
+    ///
+    /// Add a middleware, invoked in the order middlewares are added.
+    ///
+    pub fn add_middleware<M: Middleware>(&mut self, middleware: M) {
+        Arc::make_mut(&mut self.middlewares).push(middleware);
+    }
+
     ///
     /// Register a subscriber for a given event type.
     ///
-    pub fn register<E: Event + Downcast + 'static, T: Subscriber<E> + Send + Sync + 'static>(&mut self, subscriber: Arc<T>) {
+    pub fn register<E: Event + Downcast + 'static, T: Subscriber<E> + Send + Sync + 'static>(&self, subscriber: Arc<T>) -> SubscriptionId {
+        self.register_with(subscriber, SubscriberOptions::default())
+    }
+
+    ///
+    /// Register a subscriber for a given event type with its priority.
+    ///
+    pub fn register_with<E: Event + Downcast + 'static, T: Subscriber<E> + Send + Sync + 'static>(&self, subscriber: Arc<T>, options: SubscriberOptions) -> SubscriptionId {
         let event_type = TypeId::of::<E>();
 
         let handler: SubscriberClosure = Arc::new(move |event| {
             downcast_ref!(event, E)
-                .map(|event| {
-                    match subscriber.handle_event(event) {
-                        Ok(_) => {},
-                        Err(e) => {
-                            log::error!("Error while processing event: {:?}", e);
-                        }
-                    }
-                });
+                .map_or(Ok(()), |event| subscriber.handle_event(event))
         });
 
         self.subscribers
-            .entry(event_type)
-            .or_default()
-            .push(handler);
+            .write()
+            .unwrap()
+            .insert(event_type, &options, Arc::new(Registration { subscriber: type_name::<T>(), handler }))
+    }
+
+    ///
+    /// Register a subscriber for every published event.
+    ///
+    pub fn register_any<T: AnySubscriber + Send + Sync + 'static>(&self, subscriber: Arc<T>) -> SubscriptionId {
+        self.register_pattern_with(EventPattern::any(), subscriber, SubscriberOptions::default())
+    }
+
+    ///
+    /// Register a subscriber for the events whose name matches the pattern.
+    ///
+    pub fn register_pattern<T: AnySubscriber + Send + Sync + 'static>(&self, pattern: impl Into<EventPattern>, subscriber: Arc<T>) -> SubscriptionId {
+        self.register_pattern_with(pattern, subscriber, SubscriberOptions::default())
+    }
+
+    pub fn register_pattern_with<T: AnySubscriber + Send + Sync + 'static>(&self, pattern: impl Into<EventPattern>, subscriber: Arc<T>, options: SubscriberOptions) -> SubscriptionId {
+        let handler: SubscriberClosure = Arc::new(move |event| subscriber.handle_any(event));
+
+        self.subscribers
+            .write()
+            .unwrap()
+            .insert_pattern(pattern.into(), &options, Arc::new(Registration { subscriber: type_name::<T>(), handler }))
     }
 }
 
-impl EventBus for MultithreadingEventBus {
+impl MultithreadingEventBus {
     ///
-    /// Publish an event to all subscribers.
+    /// Publish an event to all subscribers, returning a handle to wait for them.
     ///
-    fn publish<E: Event>(&self, event: E) {
-        let event_type = event.type_id();
+    pub fn publish_tracked<E: Event>(&self, event: E) -> CompletionHandle {
+        let completion = CompletionHandle {
+            pending: Arc::default(),
+        };
+
+        let mut event = event;
+        if self.middlewares.before_publish(&mut event) == MiddlewareAction::Stop {
+            return completion;
+        }
+
+        let key = self.ordering_key.key_of(&event);
+        let start = Instant::now();
         let event = Arc::new(event);
 
-        if let Some(handlers) = self.subscribers.get(&event_type) {
-            for handler in handlers {
-                let handler = handler.clone();
-                let event = event.clone();
-                self.thread_pool.spawn(move || {
-                    handler(event.as_ref());
-                });
-            }
+        let registrations = self.subscribers
+                                .read()
+                                .unwrap()
+                                .matching(TypeId::of::<E>(), event.event_name());
+
+        let published = Arc::new(PublishDone { middlewares: self.middlewares.clone(), event: event.clone(), start });
+
+        let mut jobs: Vec<Job> = vec![];
+        for registration in registrations {
+            let middlewares = self.middlewares.clone();
+            let panic_handler = self.panic_handler.clone();
+            let event = event.clone();
+            let published = published.clone();
+            let done = HandlerDone(vec![completion.pending.clone(), self.pending.clone()]);
+            jobs.push(Box::new(move || {
+                // Dropped in reverse order, so `after_publish` runs before the completion is signalled.
+                let _done = done;
+                let _published = published;
+                handle(&registration, event.as_ref(), &middlewares, panic_handler.as_ref());
+            }));
+        }
+
+        completion.pending.add(jobs.len());
+        self.pending.add(jobs.len());
+
+        match (key, self.handler_execution) {
+            (Some(key), handler_execution) => {
+                if let Some(jobs) = self.lanes.enter(&key, jobs) {
+                    let lanes = self.lanes.clone();
+                    self.thread_pool.spawn(move || run_lane(&lanes, &key, jobs, handler_execution));
+                }
+            },
+            (None, HandlerExecution::Concurrent) => jobs.into_iter().for_each(|job| self.thread_pool.spawn(job)),
+            (None, HandlerExecution::Sequential) => self.thread_pool.spawn(move || jobs.into_iter().for_each(|job| job())),
         }
+
+        drop(published);
+
+        completion
+    }
+
+    ///
+    /// Block until every outstanding subscriber has run, including the ones of events published
+    /// by subscribers in the meantime.
+    ///
+    /// Must not be called from a subscriber, which would wait for itself.
+    ///
+    pub fn wait_idle(&self) {
+        self.pending.wait()
+    }
+
+    ///
+    /// Like `wait_idle`, returning false if the timeout elapses before the bus is idle.
+    ///
+    pub fn wait_idle_timeout(&self, timeout: Duration) -> bool {
+        self.pending.wait_timeout(timeout)
+    }
+}
+
+///
+/// Run a subscriber between the hooks of the middlewares, catching the panics of all of them
+/// so they never unwind a worker of the pool.
+///
+fn handle(registration: &Registration, event: &dyn Event, middlewares: &MiddlewareStack, panic_handler: Option<&PanicHandler>) {
+    let report = |payload: Box<dyn Any + Send>| {
+        let panicked = HandlerPanicked::new(event.event_name(), registration.subscriber, payload.as_ref());
+        report_panic(panic_handler, &panicked);
+        panicked
+    };
+
+    let outcome = catch_unwind(AssertUnwindSafe(|| {
+        if middlewares.before_handle(event, registration.subscriber) == MiddlewareAction::Stop {
+            return Ok(());
+        }
+
+        let handler_start = Instant::now();
+        let result = catch_unwind(AssertUnwindSafe(|| middlewares.around_handle(event, registration.subscriber, &*registration.handler)))
+            .unwrap_or_else(|payload| Err(SubscriberError::Inner(Box::new(report(payload)))));
+        middlewares.after_handle(event, registration.subscriber, &result, handler_start.elapsed());
+
+        result
+    }));
+
+    match outcome {
+        Ok(Ok(())) => {},
+        Ok(Err(e)) => log::error!("Error while processing event: {:?}", e),
+        Err(payload) => {
+            let panicked = report(payload);
+            log::error!("Error while processing event: {}", panicked);
+        },
+    }
+}
+
+///
+/// Run the subscribers of the events of a key, one event after the other.
+///
+fn run_lane(lanes: &Lanes<Vec<Job>>, key: &str, jobs: Vec<Job>, handler_execution: HandlerExecution) {
+    let mut lane = LaneGuard::new(lanes, key);
+    let mut next = Some(jobs);
+
+    while let Some(jobs) = next {
+        match handler_execution {
+            HandlerExecution::Concurrent => rayon::scope(|scope| jobs.into_iter().for_each(|job| scope.spawn(move |_| job()))),
+            HandlerExecution::Sequential => jobs.into_iter().for_each(|job| job()),
+        }
+
+        next = lane.next();
+    }
+}
+
+impl Unsubscribe for MultithreadingEventBus {
+    fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        self.subscribers.write().unwrap().remove(id)
+    }
+}
+
+impl EventBus for MultithreadingEventBus {
+    ///
+    /// Publish an event to all subscribers.
+    ///
+    /// The `after_publish` hook of the middlewares runs once all the subscribers are done, on the
+    /// thread of the last one.
+    ///
+    fn publish<E: Event>(&self, event: E) {
+        self.publish_tracked(event);
     }
 }
 
@@ -156,7 +462,7 @@ mod tests {
 
     #[test]
     fn it_should_create_a_thread_system_and_notify_and_dont_block() {
-        let mut event_bus = MultithreadingEventBus::with_num_threads(4).unwrap();
+        let event_bus = MultithreadingEventBus::with_num_threads(4).unwrap();
 
         event_bus.register(Arc::new(TestEventHandler {}));
 
@@ -171,4 +477,195 @@ mod tests {
 
         assert!(elapsed.as_secs() < 2);
     }
-}
\ No newline at end of file
+
+    struct PanickingEventHandler;
+
+    impl Subscriber<TestEvent> for PanickingEventHandler {
+        fn handle_event(&self, event: &TestEvent) -> Result<(), SubscriberError> {
+            let _ = event.tx.send(false);
+            panic!("Subscriber bug {}", 1)
+        }
+    }
+
+    #[test]
+    fn it_should_catch_and_report_panicking_subscribers() {
+        let (panics_tx, panics_rx) = channel();
+        let panics_tx = std::sync::Mutex::new(panics_tx);
+        let event_bus = MultithreadingEventBus::with_num_threads(1).unwrap().with_panic_handler(move |panicked| {
+            panics_tx.lock().unwrap().send(panicked.clone()).unwrap();
+        });
+
+        event_bus.register(Arc::new(PanickingEventHandler));
+        event_bus.register(Arc::new(TestEventHandler {}));
+
+        let (tx, rx) = channel();
+        event_bus.publish(TestEvent { tx });
+
+        assert!(!rx.recv().unwrap());
+        assert!(rx.recv().unwrap());
+        assert_eq!(panics_rx.recv().unwrap().message, "Subscriber bug 1");
+    }
+
+    #[test]
+    fn it_should_wait_for_the_subscribers_of_a_tracked_event() {
+        let event_bus = MultithreadingEventBus::with_num_threads(2).unwrap();
+        event_bus.register(Arc::new(TestEventHandler {}));
+
+        let (tx, rx) = channel();
+        let completion = event_bus.publish_tracked(TestEvent { tx });
+
+        assert!(!completion.is_finished());
+        completion.wait();
+        assert!(completion.is_finished());
+        assert!(rx.try_recv().unwrap());
+    }
+
+    #[test]
+    fn it_should_wait_until_the_bus_is_idle() {
+        let event_bus = MultithreadingEventBus::with_num_threads(2).unwrap();
+        event_bus.register(Arc::new(TestEventHandler {}));
+
+        let (tx, rx) = channel();
+        event_bus.publish(TestEvent { tx: tx.clone() });
+        event_bus.publish(TestEvent { tx });
+
+        assert!(!event_bus.wait_idle_timeout(Duration::from_millis(10)));
+        event_bus.wait_idle();
+        assert_eq!(rx.try_iter().count(), 2);
+    }
+
+    struct KeyedEvent {
+        key: &'static str,
+        sequence: usize,
+        handled: Arc<std::sync::Mutex<Vec<(&'static str, usize)>>>,
+    }
+
+    impl Event for KeyedEvent {
+        fn event_name(&self) -> &'static str {
+            "keyed_event"
+        }
+
+        fn ordering_key(&self) -> Option<String> {
+            Some(self.key.to_string())
+        }
+    }
+
+    struct KeyedEventHandler;
+
+    impl Subscriber<KeyedEvent> for KeyedEventHandler {
+        fn handle_event(&self, event: &KeyedEvent) -> Result<(), SubscriberError> {
+            sleep(Duration::from_millis(50 / (event.sequence as u64 + 1)));
+            event.handled.lock().unwrap().push((event.key, event.sequence));
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_should_handle_the_events_of_a_key_in_order() {
+        let event_bus = MultithreadingEventBus::with_num_threads(4).unwrap();
+        event_bus.register(Arc::new(KeyedEventHandler));
+
+        let handled = Arc::new(std::sync::Mutex::new(vec![]));
+        for sequence in 0..4 {
+            for key in ["a", "b"] {
+                event_bus.publish(KeyedEvent { key, sequence, handled: handled.clone() });
+            }
+        }
+        event_bus.wait_idle();
+
+        let handled = handled.lock().unwrap();
+        for key in ["a", "b"] {
+            let sequences: Vec<usize> = handled.iter().filter(|(k, _)| *k == key).map(|(_, sequence)| *sequence).collect();
+            assert_eq!(sequences, vec![0, 1, 2, 3]);
+        }
+    }
+
+    struct Auditor {
+        audited: std::sync::Mutex<Vec<&'static str>>
+    }
+
+    impl AnySubscriber for Auditor {
+        fn handle_any(&self, event: &dyn Event) -> Result<(), SubscriberError> {
+            self.audited.lock().unwrap().push(event.event_name());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_should_deliver_events_to_catch_all_and_pattern_subscribers() {
+        let event_bus = MultithreadingEventBus::with_num_threads(2).unwrap();
+
+        let auditor = Arc::new(Auditor { audited: std::sync::Mutex::new(vec![]) });
+        let keyed_auditor = Arc::new(Auditor { audited: std::sync::Mutex::new(vec![]) });
+        let test_auditor = Arc::new(Auditor { audited: std::sync::Mutex::new(vec![]) });
+        event_bus.register_any(auditor.clone());
+        event_bus.register_pattern("keyed_*", keyed_auditor.clone());
+        event_bus.register_pattern_with("test_*", test_auditor.clone(), SubscriberOptions::default().with_priority(1));
+
+        let (tx, _rx) = channel();
+        event_bus.publish(TestEvent { tx });
+        event_bus.wait_idle();
+        event_bus.publish(KeyedEvent { key: "a", sequence: 0, handled: Arc::new(std::sync::Mutex::new(vec![])) });
+        event_bus.wait_idle();
+
+        assert_eq!(*auditor.audited.lock().unwrap(), vec!["test_event", "keyed_event"]);
+        assert_eq!(*keyed_auditor.audited.lock().unwrap(), vec!["keyed_event"]);
+        assert_eq!(*test_auditor.audited.lock().unwrap(), vec!["test_event"]);
+    }
+
+    struct PanickingMiddleware;
+
+    impl Middleware for PanickingMiddleware {
+        fn after_handle(&self, _event: &dyn Event, _subscriber: &'static str, _result: &Result<(), SubscriberError>, _elapsed: Duration) {
+            panic!("Middleware bug")
+        }
+    }
+
+    #[test]
+    fn it_should_catch_and_report_panicking_middlewares() {
+        let (panics_tx, panics_rx) = channel();
+        let panics_tx = std::sync::Mutex::new(panics_tx);
+        let mut event_bus = MultithreadingEventBus::with_num_threads(1).unwrap().with_panic_handler(move |panicked| {
+            panics_tx.lock().unwrap().send(panicked.clone()).unwrap();
+        });
+        event_bus.add_middleware(PanickingMiddleware);
+
+        let auditor = Arc::new(Auditor { audited: std::sync::Mutex::new(vec![]) });
+        event_bus.register_any(auditor.clone());
+
+        let (tx, _rx) = channel();
+        event_bus.publish(TestEvent { tx: tx.clone() });
+        event_bus.publish(TestEvent { tx });
+        event_bus.wait_idle();
+
+        assert_eq!(auditor.audited.lock().unwrap().len(), 2);
+        assert_eq!(panics_rx.try_iter().map(|panicked| panicked.message).collect::<Vec<_>>(), vec!["Middleware bug", "Middleware bug"]);
+    }
+
+    struct PublishTimes {
+        elapsed: Arc<std::sync::Mutex<Vec<Duration>>>
+    }
+
+    impl Middleware for PublishTimes {
+        fn after_publish(&self, _event: &dyn Event, elapsed: Duration) {
+            self.elapsed.lock().unwrap().push(elapsed);
+        }
+    }
+
+    #[test]
+    fn it_should_run_after_publish_once_the_subscribers_are_done() {
+        let elapsed = Arc::new(std::sync::Mutex::new(vec![]));
+        let mut event_bus = MultithreadingEventBus::with_num_threads(2).unwrap();
+        event_bus.add_middleware(PublishTimes { elapsed: elapsed.clone() });
+        event_bus.register(Arc::new(KeyedEventHandler));
+
+        let completion = event_bus.publish_tracked(KeyedEvent { key: "a", sequence: 0, handled: Arc::new(std::sync::Mutex::new(vec![])) });
+        assert!(elapsed.lock().unwrap().is_empty());
+
+        completion.wait();
+        let elapsed = elapsed.lock().unwrap();
+        assert_eq!(elapsed.len(), 1);
+        assert!(elapsed[0] >= Duration::from_millis(50));
+    }
+}
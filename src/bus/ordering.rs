@@ -0,0 +1,198 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use crate::event::Event;
+
+///
+/// Where the concurrent buses take the key of an event from. Events with the same key are handled
+/// one after the other, in the order they are published, while different keys run in parallel.
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OrderingKey {
+    ///
+    /// The key returned by `Event::ordering_key`.
+    ///
+    #[default]
+    Event,
+    ///
+    /// The value of a metadata entry of the event. Events without the entry are not ordered, while
+    /// events not exposing their metadata through `Event::event_metadata` are ordered by their name.
+    ///
+    Metadata(&'static str),
+}
+
+impl OrderingKey {
+    pub(crate) fn key_of(&self, event: &dyn Event) -> Option<String> {
+        match self {
+            OrderingKey::Event => event.ordering_key(),
+            OrderingKey::Metadata(key) => match event.event_metadata() {
+                Some(metadata) => metadata.get(key).cloned(),
+                None => {
+                    log::warn!("Event {} does not expose its metadata, it is ordered by its name", event.event_name());
+                    Some(event.event_name().to_string())
+                }
+            },
+        }
+    }
+}
+
+///
+/// The work waiting for its turn, one lane per ordering key.
+///
+/// A lane exists while some work of its key is running, and whoever runs it takes the next item
+/// until the lane is empty.
+///
+pub(crate) struct Lanes<T> {
+    lanes: Mutex<HashMap<String, VecDeque<T>>>,
+}
+
+impl<T> Default for Lanes<T> {
+    fn default() -> Self {
+        Lanes {
+            lanes: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T> Lanes<T> {
+    ///
+    /// Queue the work behind the running one of its key, or give it back to be run right away.
+    ///
+    pub(crate) fn enter(&self, key: &str, work: T) -> Option<T> {
+        let mut lanes = self.lanes.lock().unwrap();
+
+        match lanes.get_mut(key) {
+            Some(lane) => {
+                lane.push_back(work);
+                None
+            },
+            None => {
+                lanes.insert(key.to_string(), VecDeque::new());
+                Some(work)
+            }
+        }
+    }
+
+    ///
+    /// Take the next work of the key, closing the lane when there is none.
+    ///
+    pub(crate) fn next(&self, key: &str) -> Option<T> {
+        let mut lanes = self.lanes.lock().unwrap();
+
+        let next = lanes.get_mut(key).and_then(|lane| lane.pop_front());
+        if next.is_none() {
+            lanes.remove(key);
+        }
+
+        next
+    }
+
+    ///
+    /// Close the lane of the key, dropping the work still queued in it.
+    ///
+    fn close(&self, key: &str) -> usize {
+        self.lanes.lock().unwrap().remove(key).map_or(0, |lane| lane.len())
+    }
+}
+
+///
+/// Runs the lane of a key, closing it when dropped before the lane is empty, e.g. when the
+/// work panics, so that later work of the key is not queued forever.
+///
+pub(crate) struct LaneGuard<'a, T> {
+    lanes: &'a Lanes<T>,
+    key: &'a str,
+    closed: bool,
+}
+
+impl<'a, T> LaneGuard<'a, T> {
+    pub(crate) fn new(lanes: &'a Lanes<T>, key: &'a str) -> Self {
+        LaneGuard { lanes, key, closed: false }
+    }
+
+    ///
+    /// Take the next work of the key, like `Lanes::next`.
+    ///
+    pub(crate) fn next(&mut self) -> Option<T> {
+        let next = self.lanes.next(self.key);
+        self.closed = next.is_none();
+        next
+    }
+}
+
+impl<T> Drop for LaneGuard<'_, T> {
+    fn drop(&mut self) {
+        if !self.closed {
+            let dropped = self.lanes.close(self.key);
+            log::error!("Lane of key {} closed before its end, dropping {} queued events", self.key, dropped);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::EventMetadata;
+
+    use super::*;
+
+    struct KeyedEvent {
+        metadata: EventMetadata
+    }
+
+    impl Event for KeyedEvent {
+        fn event_name(&self) -> &'static str {
+            "keyed_event"
+        }
+
+        fn event_metadata(&self) -> Option<&EventMetadata> {
+            Some(&self.metadata)
+        }
+    }
+
+    struct HandWrittenEvent;
+
+    impl Event for HandWrittenEvent {
+        fn event_name(&self) -> &'static str {
+            "hand_written_event"
+        }
+    }
+
+    #[test]
+    fn it_should_take_the_key_from_the_metadata_or_order_by_name_without_metadata() {
+        let key = OrderingKey::Metadata("aggregate-id");
+        let mut event = KeyedEvent { metadata: EventMetadata::default() };
+
+        assert_eq!(key.key_of(&event), None);
+        event.metadata.add("aggregate-id".to_string(), "1".to_string());
+        assert_eq!(key.key_of(&event), Some("1".to_string()));
+        assert_eq!(key.key_of(&HandWrittenEvent), Some("hand_written_event".to_string()));
+    }
+
+    #[test]
+    fn it_should_queue_work_behind_the_running_one_of_its_key() {
+        let lanes = Lanes::default();
+
+        assert_eq!(lanes.enter("a", 1), Some(1));
+        assert_eq!(lanes.enter("a", 2), None);
+        assert_eq!(lanes.enter("b", 3), Some(3));
+
+        assert_eq!(lanes.next("a"), Some(2));
+        assert_eq!(lanes.next("a"), None);
+        assert_eq!(lanes.enter("a", 4), Some(4));
+    }
+
+    #[test]
+    fn it_should_close_the_lane_when_its_guard_is_dropped_before_the_end() {
+        let lanes = Lanes::default();
+        assert_eq!(lanes.enter("a", 1), Some(1));
+        assert_eq!(lanes.enter("a", 2), None);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = LaneGuard::new(&lanes, "a");
+            panic!("Work panicked");
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(lanes.enter("a", 3), Some(3));
+    }
+}
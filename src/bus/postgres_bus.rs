@@ -0,0 +1,59 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::bus::AsynchronousEventBus;
+use crate::bus::error::PublishError;
+use crate::bus::middleware::{Middleware, MiddlewareAction, MiddlewareStack};
+use crate::event::{Event, EventWithMetadata};
+use crate::postgres::postgres_publisher::PostgresPublisher;
+use crate::serializer::EventSerializer;
+
+pub struct PostgresEventBus<'a, T: EventSerializer> {
+    serializer: &'a T,
+    table: String,
+    middlewares: MiddlewareStack,
+    publisher: Arc<PostgresPublisher>
+}
+
+impl<'a, T: EventSerializer> PostgresEventBus<'a, T> {
+    pub fn new(
+        publisher: Arc<PostgresPublisher>,
+        serializer: &'a T,
+        table: String
+    ) -> Self {
+        Self {
+            serializer,
+            table,
+            middlewares: MiddlewareStack::default(),
+            publisher
+        }
+    }
+
+    ///
+    /// Add a middleware. Subscribers run in the consumers, so only the publish hooks are invoked.
+    ///
+    pub fn add_middleware<M: Middleware>(&mut self, middleware: M) {
+        self.middlewares.push(middleware);
+    }
+}
+
+impl<T: EventSerializer> AsynchronousEventBus for PostgresEventBus<'_, T> {
+    async fn publish<E: Event + EventWithMetadata + Serialize>(&self, event: E) -> Result<(), PublishError> {
+        let mut event = event;
+        if self.middlewares.before_publish_with_metadata(&mut event) == MiddlewareAction::Stop {
+            return Ok(());
+        }
+
+        self.serializer.stamp(&mut event);
+
+        let start = Instant::now();
+        let payload = self.serializer.serialize(&event).map_err(|e| PublishError::CannotSerializeEvent(Box::new(e)))?;
+
+        self.publisher.publish(&payload, event.event_name(), self.table.as_str()).await?;
+        self.middlewares.after_publish(&event, start.elapsed());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use crate::subscriber::SubscriberError;
+
+///
+/// What to do with the remaining subscribers once one of them fails.
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FailurePolicy {
+    #[default]
+    Continue,
+    FailFast,
+}
+
+#[derive(Debug)]
+pub struct SubscriberOutcome {
+    pub subscriber: &'static str,
+    pub result: Result<(), SubscriberError>,
+}
+
+///
+/// The outcome of every subscriber that handled a published event.
+///
+/// Subscribers that were not invoked because of `FailurePolicy::FailFast` are counted as skipped.
+///
+#[derive(Debug)]
+pub struct PublishReport {
+    pub event_name: &'static str,
+    pub outcomes: Vec<SubscriberOutcome>,
+    pub skipped: usize,
+}
+
+impl PublishReport {
+    pub fn new(event_name: &'static str) -> Self {
+        PublishReport {
+            event_name,
+            outcomes: Vec::new(),
+            skipped: 0,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.skipped == 0 && self.outcomes.iter().all(|outcome| outcome.result.is_ok())
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &SubscriberOutcome> {
+        self.outcomes.iter().filter(|outcome| outcome.result.is_err())
+    }
+}
@@ -1,9 +1,11 @@
 use std::sync::Arc;
+use std::time::Instant;
 
 use serde::Serialize;
 
 use crate::bus::AsynchronousEventBus;
 use crate::bus::error::PublishError;
+use crate::bus::middleware::{Middleware, MiddlewareAction, MiddlewareStack};
 use crate::event::{Event, EventWithMetadata};
 use crate::rabbit::rabbit_publisher::RabbitPublisher;
 use crate::serializer::EventSerializer;
@@ -11,6 +13,7 @@ use crate::serializer::EventSerializer;
 pub struct RabbitEventBus<'a, T: EventSerializer> {
     serializer: &'a T,
     exchange: String,
+    middlewares: MiddlewareStack,
     publisher: Arc<RabbitPublisher>
 }
 
@@ -23,15 +26,34 @@ impl<'a, T: EventSerializer> RabbitEventBus<'a, T> {
         Self {
             serializer,
             exchange,
+            middlewares: MiddlewareStack::default(),
             publisher
         }
     }
+
+    ///
+    /// Add a middleware, only its publish hooks are invoked since subscribers live in the consumers.
+    ///
+    pub fn add_middleware<M: Middleware>(&mut self, middleware: M) {
+        self.middlewares.push(middleware);
+    }
 }
 
 impl<T: EventSerializer> AsynchronousEventBus for RabbitEventBus<'_, T> {
     async fn publish<E: Event + EventWithMetadata + Serialize>(&self, event: E) -> Result<(), PublishError> {
-        let payload = self.serializer.serialize(&event).map_err(|_| PublishError::CannotSerializeEvent)?;
+        let mut event = event;
+        if self.middlewares.before_publish_with_metadata(&mut event) == MiddlewareAction::Stop {
+            return Ok(());
+        }
+
+        self.serializer.stamp(&mut event);
+
+        let start = Instant::now();
+        let (payload, properties) = self.serializer.serialize_amqp(&event).map_err(|e| PublishError::CannotSerializeEvent(Box::new(e)))?;
+
+        self.publisher.publish_with_properties(&payload, event.event_name(), self.exchange.as_str(), properties).await?;
+        self.middlewares.after_publish(&event, start.elapsed());
 
-        self.publisher.publish(payload.as_bytes(), event.event_name(), self.exchange.as_str()).await
+        Ok(())
     }
 }
@@ -0,0 +1,200 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::event::Event;
+use crate::subscriber::SubscriberError;
+
+///
+/// How many times a failing subscriber is run again, and how long to wait between attempts.
+///
+/// The wait doubles after every attempt by default, up to the maximum backoff.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: u32,
+    max_requeues: u32,
+    retryable: fn(&SubscriberError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2,
+            max_requeues: 3,
+            retryable: SubscriberError::is_retryable,
+        }
+    }
+}
+
+impl RetryPolicy {
+    ///
+    /// Run a failing subscriber up to `max_attempts` times, including the first one.
+    ///
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    ///
+    /// Run a subscriber answering `SubscriberError::Requeue` again up to `max_requeues` times.
+    /// Requeues do not count as attempts.
+    ///
+    pub fn with_max_requeues(mut self, max_requeues: u32) -> Self {
+        self.max_requeues = max_requeues;
+        self
+    }
+
+    ///
+    /// Only retry the errors for which `retryable` returns true, instead of the ones
+    /// `SubscriberError::is_retryable` accepts.
+    ///
+    pub fn retry_if(mut self, retryable: fn(&SubscriberError) -> bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    ///
+    /// Whether the subscriber should run again after failing its `attempt` with the error.
+    ///
+    pub fn should_retry(&self, attempt: u32, error: &SubscriberError) -> bool {
+        match error {
+            SubscriberError::Requeue => false,
+            error => attempt < self.max_attempts && (self.retryable)(error),
+        }
+    }
+
+    ///
+    /// Whether the subscriber should run again after asking to be requeued `requeues` times.
+    ///
+    pub fn should_requeue(&self, requeues: u32) -> bool {
+        requeues < self.max_requeues
+    }
+
+    ///
+    /// The time to wait after the failed `attempt` with the error, unless the error asks for its own delay.
+    ///
+    pub fn delay(&self, attempt: u32, error: &SubscriberError) -> Duration {
+        error.delay().unwrap_or_else(|| self.backoff(attempt))
+    }
+
+    ///
+    /// The time to wait after the failed `attempt`, starting at 1.
+    ///
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(self.multiplier.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+}
+
+///
+/// An event a subscriber failed to handle, after running out of attempts or with an error that is not retried.
+///
+/// Events discarded with `SubscriberError::UnrecoverableError`, or still asking to be requeued
+/// once out of requeues, are not dead lettered.
+///
+#[derive(Clone)]
+pub struct DeadLetter {
+    pub event_name: &'static str,
+    pub subscriber: &'static str,
+    pub attempts: u32,
+    pub error: String,
+    pub event: Arc<dyn Event>,
+}
+
+///
+/// Keeps the dead letters in memory until they are taken.
+///
+#[derive(Default)]
+pub struct DeadLetterSink {
+    dead_letters: Mutex<Vec<DeadLetter>>,
+}
+
+impl DeadLetterSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn push(&self, dead_letter: DeadLetter) {
+        self.dead_letters.lock().unwrap().push(dead_letter);
+    }
+
+    pub fn len(&self) -> usize {
+        self.dead_letters.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///
+    /// Take all the dead letters, e.g. to publish their events again.
+    ///
+    pub fn drain(&self) -> Vec<DeadLetter> {
+        std::mem::take(&mut *self.dead_letters.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_back_off_exponentially_up_to_the_maximum() {
+        let policy = RetryPolicy::new(5).with_backoff(Duration::from_millis(100), Duration::from_millis(350));
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(350));
+    }
+
+    #[test]
+    fn it_should_not_retry_unrecoverable_errors_or_after_the_last_attempt() {
+        let policy = RetryPolicy::new(2);
+
+        assert!(policy.should_retry(1, &SubscriberError::Inner("Timeout".into())));
+        assert!(!policy.should_retry(2, &SubscriberError::Inner("Timeout".into())));
+        assert!(!policy.should_retry(1, &SubscriberError::UnrecoverableError));
+        assert!(!policy.should_retry(1, &SubscriberError::permanent("Invalid event")));
+    }
+
+    #[test]
+    fn it_should_requeue_apart_from_the_attempts() {
+        let policy = RetryPolicy::new(1).with_max_requeues(2);
+
+        assert!(!policy.should_retry(1, &SubscriberError::Requeue));
+        assert!(policy.should_requeue(1));
+        assert!(!policy.should_requeue(2));
+    }
+
+    #[test]
+    fn it_should_wait_for_the_delay_asked_by_the_error() {
+        let policy = RetryPolicy::new(3);
+
+        assert_eq!(policy.delay(1, &SubscriberError::retryable("Rate limited").with_delay(Duration::from_secs(30))), Duration::from_secs(30));
+        assert_eq!(policy.delay(1, &SubscriberError::retryable("Rate limited")), Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,258 @@
+use std::any::TypeId;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::bus::event_pattern::EventPattern;
+use crate::bus::retry::RetryPolicy;
+
+///
+/// Identifies a registered subscriber so it can be removed from the bus.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+impl SubscriptionId {
+    fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+
+        SubscriptionId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+///
+/// How a subscriber is registered.
+///
+/// Subscribers with a higher priority handle an event first, and equal priorities keep the
+/// registration order, typed subscribers before pattern ones.
+///
+#[derive(Debug, Clone, Default)]
+pub struct SubscriberOptions {
+    priority: i32,
+    timeout: Option<Duration>,
+    max_concurrency: Option<usize>,
+    retry_policy: Option<RetryPolicy>,
+}
+
+impl SubscriberOptions {
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    ///
+    /// Cancel the subscriber when it takes longer, overriding the timeout of the bus.
+    ///
+    /// Only the Tokio bus can cancel subscribers.
+    ///
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    ///
+    /// Limit how many events the subscriber handles at the same time.
+    ///
+    /// Only the Tokio bus limits subscribers.
+    ///
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = Some(max_concurrency);
+        self
+    }
+
+    ///
+    /// Retry the subscriber when it fails, overriding the retry policy of the bus.
+    ///
+    /// Only the Tokio bus retries subscribers.
+    ///
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn max_concurrency(&self) -> Option<usize> {
+        self.max_concurrency
+    }
+
+    pub fn retry_policy(&self) -> Option<RetryPolicy> {
+        self.retry_policy
+    }
+}
+
+///
+/// How the asynchronous buses run the subscribers of an event.
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HandlerExecution {
+    #[default]
+    Concurrent,
+    ///
+    /// One subscriber after the other, by priority.
+    ///
+    Sequential,
+}
+
+pub trait Unsubscribe {
+    ///
+    /// Remove a subscriber, returning whether it was registered.
+    ///
+    fn unsubscribe(&self, id: SubscriptionId) -> bool;
+}
+
+///
+/// Unsubscribes when dropped.
+///
+/// `P` is anything pointing to the bus, e.g. `&SynchronousEventBus` or `Arc<TokioEventBus>`.
+///
+pub struct SubscriptionGuard<P: Deref>
+where
+    P::Target: Unsubscribe
+{
+    bus: P,
+    id: SubscriptionId,
+    active: bool,
+}
+
+impl<P: Deref> SubscriptionGuard<P>
+where
+    P::Target: Unsubscribe
+{
+    pub fn new(bus: P, id: SubscriptionId) -> Self {
+        SubscriptionGuard {
+            bus,
+            id,
+            active: true,
+        }
+    }
+
+    pub fn id(&self) -> SubscriptionId {
+        self.id
+    }
+
+    pub fn unsubscribe(mut self) -> bool {
+        self.active = false;
+        self.bus.unsubscribe(self.id)
+    }
+
+    ///
+    /// Keep the subscriber registered after the guard is dropped.
+    ///
+    pub fn detach(mut self) -> SubscriptionId {
+        self.active = false;
+        self.id
+    }
+}
+
+impl<P: Deref> Drop for SubscriptionGuard<P>
+where
+    P::Target: Unsubscribe
+{
+    fn drop(&mut self) {
+        if self.active {
+            self.bus.unsubscribe(self.id);
+        }
+    }
+}
+
+///
+/// The subscribers of an in-process bus, by event type and by event name pattern.
+///
+pub(crate) struct SubscriberRegistry<R> {
+    typed: HashMap<TypeId, Vec<Entry<R>>>,
+    wildcards: Vec<(EventPattern, Entry<R>)>,
+}
+
+#[derive(Clone)]
+struct Entry<R> {
+    id: SubscriptionId,
+    priority: i32,
+    registration: R,
+}
+
+impl<R: Clone> Clone for SubscriberRegistry<R> {
+    fn clone(&self) -> Self {
+        SubscriberRegistry {
+            typed: self.typed.clone(),
+            wildcards: self.wildcards.clone(),
+        }
+    }
+}
+
+impl<R> Default for SubscriberRegistry<R> {
+    fn default() -> Self {
+        SubscriberRegistry {
+            typed: HashMap::new(),
+            wildcards: Vec::new(),
+        }
+    }
+}
+
+impl<R: Clone> SubscriberRegistry<R> {
+    pub(crate) fn insert(&mut self, event_type: TypeId, options: &SubscriberOptions, registration: R) -> SubscriptionId {
+        let id = SubscriptionId::next();
+
+        self.typed
+            .entry(event_type)
+            .or_default()
+            .push(Entry { id, priority: options.priority, registration });
+
+        id
+    }
+
+    pub(crate) fn insert_pattern(&mut self, pattern: EventPattern, options: &SubscriberOptions, registration: R) -> SubscriptionId {
+        let id = SubscriptionId::next();
+
+        self.wildcards.push((pattern, Entry { id, priority: options.priority, registration }));
+
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: SubscriptionId) -> bool {
+        for entries in self.typed.values_mut() {
+            if let Some(index) = entries.iter().position(|entry| entry.id == id) {
+                entries.remove(index);
+                return true;
+            }
+        }
+
+        match self.wildcards.iter().position(|(_, entry)| entry.id == id) {
+            Some(index) => {
+                self.wildcards.remove(index);
+                true
+            },
+            None => false,
+        }
+    }
+
+    ///
+    /// The subscribers of an event, by descending priority.
+    ///
+    pub(crate) fn matching(&self, event_type: TypeId, event_name: &str) -> Vec<R> {
+        let typed = self.typed
+                        .get(&event_type)
+                        .into_iter()
+                        .flatten();
+
+        let wildcards = self.wildcards
+                            .iter()
+                            .filter(|(pattern, _)| pattern.matches(event_name))
+                            .map(|(_, entry)| entry);
+
+        let mut entries: Vec<&Entry<R>> = typed.chain(wildcards).collect();
+        entries.sort_by_key(|entry| Reverse(entry.priority));
+
+        entries.into_iter()
+               .map(|entry| entry.registration.clone())
+               .collect()
+    }
+}
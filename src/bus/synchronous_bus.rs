@@ -1,31 +1,93 @@
-use std::any::TypeId;
-use std::collections::HashMap;
+use std::any::{type_name, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
 use std::rc::Rc;
+use std::time::Instant;
 
 use downcaster::{Downcast, downcast_ref};
 
 use crate::bus::EventBus;
+use crate::bus::event_pattern::EventPattern;
+use crate::bus::middleware::{Middleware, MiddlewareAction, MiddlewareStack};
+use crate::bus::publish_report::{FailurePolicy, PublishReport, SubscriberOutcome};
+use crate::bus::subscription::{SubscriberOptions, SubscriberRegistry, SubscriptionId, Unsubscribe};
 use crate::event::Event;
-use crate::subscriber::Subscriber;
+use crate::subscriber::{AnySubscriber, Subscriber, SubscriberError};
 
-type SubscriberClosure = Box<dyn Fn(&dyn Event)>;
+type SubscriberClosure = Box<dyn Fn(&dyn Event) -> Result<(), SubscriberError>>;
+
+type DeferredEvent = Box<dyn FnOnce(&SynchronousEventBus)>;
+
+struct Registration {
+    subscriber: &'static str,
+    handler: SubscriberClosure,
+}
+
+///
+/// How events published from inside a subscriber are handled.
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DispatchMode {
+    ///
+    /// Handle the event right away, before the current dispatch continues.
+    ///
+    #[default]
+    Immediate,
+    ///
+    /// Queue the event and handle it once the current dispatch ends, breadth-first.
+    ///
+    Deferred,
+}
 
 ///
 /// A synchronous event bus that handles events synchronously.
 /// 
 #[derive(Default)]
 pub struct SynchronousEventBus {
-    subscribers: HashMap<TypeId, Vec<SubscriberClosure>>
+    subscribers: RefCell<SubscriberRegistry<Rc<Registration>>>,
+    middlewares: MiddlewareStack,
+    dispatch_mode: DispatchMode,
+    dispatching: Cell<bool>,
+    deferred: RefCell<VecDeque<DeferredEvent>>,
+}
+
+struct Dispatching<'a>(&'a Cell<bool>);
+
+impl Drop for Dispatching<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
 }
 
 impl SynchronousEventBus {
     pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Create a bus where subscribers publishing follow-up events, e.g. through a `Weak` reference
+    /// to the bus, get them queued or handled immediately.
+    ///
+    pub fn with_dispatch_mode(dispatch_mode: DispatchMode) -> Self {
         SynchronousEventBus {
-            subscribers: HashMap::new()
+            dispatch_mode,
+            ..Self::default()
         }
     }
 
-    pub fn register<E, S>(&mut self, subscriber: Rc<S>)
+    pub fn add_middleware<M: Middleware>(&mut self, middleware: M) {
+        self.middlewares.push(middleware);
+    }
+
+    pub fn register<E, S>(&self, subscriber: Rc<S>) -> SubscriptionId
+    where
+        E: Event + Downcast + 'static,
+        S: Subscriber<E> + 'static
+    {
+        self.register_with(subscriber, SubscriberOptions::default())
+    }
+
+    pub fn register_with<E, S>(&self, subscriber: Rc<S>, options: SubscriberOptions) -> SubscriptionId
     where
         E: Event + Downcast + 'static,
         S: Subscriber<E> + 'static
@@ -34,31 +96,121 @@ impl SynchronousEventBus {
 
         let handler: SubscriberClosure = Box::new(move |event| {
             downcast_ref!(event, E)
-                .map(|event| {
-                    match subscriber.handle_event(event) {
-                        Ok(_) => {},
-                        Err(e) => {
-                            log::error!("Error while processing event: {:?}", e);
-                        }
-                    }
-                });
+                .map_or(Ok(()), |event| subscriber.handle_event(event))
         });
 
         self.subscribers
-            .entry(event_type)
-            .or_default()
-            .push(handler);
+            .borrow_mut()
+            .insert(event_type, &options, Rc::new(Registration { subscriber: type_name::<S>(), handler }))
+    }
+
+    ///
+    /// Register a subscriber for every published event.
+    ///
+    pub fn register_any<S: AnySubscriber + 'static>(&self, subscriber: Rc<S>) -> SubscriptionId {
+        self.register_pattern_with(EventPattern::any(), subscriber, SubscriberOptions::default())
+    }
+
+    ///
+    /// Register a subscriber for the events whose name matches the pattern.
+    ///
+    pub fn register_pattern<S: AnySubscriber + 'static>(&self, pattern: impl Into<EventPattern>, subscriber: Rc<S>) -> SubscriptionId {
+        self.register_pattern_with(pattern, subscriber, SubscriberOptions::default())
+    }
+
+    pub fn register_pattern_with<S: AnySubscriber + 'static>(&self, pattern: impl Into<EventPattern>, subscriber: Rc<S>, options: SubscriberOptions) -> SubscriptionId {
+        let handler: SubscriberClosure = Box::new(move |event| subscriber.handle_any(event));
+
+        self.subscribers
+            .borrow_mut()
+            .insert_pattern(pattern.into(), &options, Rc::new(Registration { subscriber: type_name::<S>(), handler }))
+    }
+
+    ///
+    /// Publish an event and report the outcome of every subscriber instead of only logging failures.
+    ///
+    /// The event is always handled immediately, whatever the dispatch mode.
+    ///
+    pub fn try_publish<T: Event>(&self, event: T, policy: FailurePolicy) -> PublishReport {
+        if self.dispatching.replace(true) {
+            return self.dispatch(event, policy);
+        }
+
+        let _dispatching = Dispatching(&self.dispatching);
+        let report = self.dispatch(event, policy);
+
+        loop {
+            let deferred = self.deferred.borrow_mut().pop_front();
+            match deferred {
+                Some(deferred) => deferred(self),
+                None => break,
+            }
+        }
+
+        report
+    }
+
+    fn dispatch<T: Event>(&self, event: T, policy: FailurePolicy) -> PublishReport {
+        let mut event = event;
+        let mut report = PublishReport::new(event.event_name());
+
+        if self.middlewares.before_publish(&mut event) == MiddlewareAction::Stop {
+            return report;
+        }
+
+        let start = Instant::now();
+        let registrations = self.subscribers
+                                .borrow()
+                                .matching(TypeId::of::<T>(), event.event_name());
+
+        for (index, registration) in registrations.iter().enumerate() {
+            if self.middlewares.before_handle(&event, registration.subscriber) == MiddlewareAction::Stop {
+                continue;
+            }
+
+            let handler_start = Instant::now();
+            let result = self.middlewares.around_handle(&event, registration.subscriber, &*registration.handler);
+            self.middlewares.after_handle(&event, registration.subscriber, &result, handler_start.elapsed());
+
+            let failed = result.is_err();
+
+            report.outcomes.push(SubscriberOutcome { subscriber: registration.subscriber, result });
+
+            if failed && policy == FailurePolicy::FailFast {
+                report.skipped = registrations.len() - index - 1;
+                break;
+            }
+        }
+
+        self.middlewares.after_publish(&event, start.elapsed());
+
+        report
+    }
+}
+
+impl Unsubscribe for SynchronousEventBus {
+    fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        self.subscribers.borrow_mut().remove(id)
     }
 }
 
 impl EventBus for SynchronousEventBus {
     fn publish<T: Event>(&self, event: T) {
-        let event_type = TypeId::of::<T>();
+        if self.dispatch_mode == DispatchMode::Deferred && self.dispatching.get() {
+            self.deferred
+                .borrow_mut()
+                .push_back(Box::new(move |bus| log_failures(bus.dispatch(event, FailurePolicy::Continue))));
+            return;
+        }
 
-        if let Some(handlers) = self.subscribers.get(&event_type) {
-            for handler in handlers {
-                handler(&event);
-            }
+        log_failures(self.try_publish(event, FailurePolicy::Continue));
+    }
+}
+
+fn log_failures(report: PublishReport) {
+    for outcome in report.failures() {
+        if let Err(e) = &outcome.result {
+            log::error!("Error while processing event: {:?}", e);
         }
     }
 }
@@ -68,6 +220,7 @@ mod tests {
     use std::cell::RefCell;
     use std::rc::Rc;
 
+    use crate::bus::subscription::SubscriptionGuard;
     use crate::impl_event_handler;
     use crate::subscriber::SubscriberError;
 
@@ -104,9 +257,7 @@ mod tests {
 
     #[test]
     fn it_should_publish_and_modify_state() {
-        let mut event_bus = SynchronousEventBus {
-            subscribers: HashMap::new()
-        };
+        let event_bus = SynchronousEventBus::new();
 
         let handler = Rc::new(TestEventHandler { total_messages_received: RefCell::new(0) });
         event_bus.register(handler.clone());
@@ -116,4 +267,217 @@ mod tests {
 
         assert_eq!(*handler.clone().total_messages_received.borrow(), 1);
     }
-}
\ No newline at end of file
+
+    struct FailingHandler;
+
+    impl FailingHandler {
+        fn on_test_event(&self, _event: &TestEvent) -> Result<(), SubscriberError> {
+            Err(SubscriberError::UnrecoverableError)
+        }
+    }
+
+    impl_event_handler!(FailingHandler, on_test_event, TestEvent);
+
+    #[test]
+    fn it_should_report_the_outcome_of_every_subscriber() {
+        let event_bus = SynchronousEventBus::new();
+
+        let handler = Rc::new(TestEventHandler { total_messages_received: RefCell::new(0) });
+        event_bus.register(Rc::new(FailingHandler));
+        event_bus.register(handler.clone());
+
+        let report = event_bus.try_publish(TestEvent {}, FailurePolicy::Continue);
+
+        assert!(!report.is_success());
+        assert_eq!(report.outcomes.len(), 2);
+        assert_eq!(report.failures().count(), 1);
+        assert!(report.failures().all(|outcome| outcome.subscriber.ends_with("FailingHandler")));
+        assert_eq!(*handler.total_messages_received.borrow(), 1);
+    }
+
+    #[test]
+    fn it_should_stop_at_the_first_failure_when_failing_fast() {
+        let event_bus = SynchronousEventBus::new();
+
+        let handler = Rc::new(TestEventHandler { total_messages_received: RefCell::new(0) });
+        event_bus.register(Rc::new(FailingHandler));
+        event_bus.register(handler.clone());
+
+        let report = event_bus.try_publish(TestEvent {}, FailurePolicy::FailFast);
+
+        assert_eq!(report.outcomes.len(), 1);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(*handler.total_messages_received.borrow(), 0);
+        assert!(event_bus.try_publish(OtherTestEvent {}, FailurePolicy::FailFast).is_success());
+    }
+
+    struct SkipFailingHandler;
+
+    impl Middleware for SkipFailingHandler {
+        fn before_handle(&self, _event: &dyn Event, subscriber: &'static str) -> MiddlewareAction {
+            match subscriber.ends_with("FailingHandler") {
+                true => MiddlewareAction::Stop,
+                false => MiddlewareAction::Continue,
+            }
+        }
+    }
+
+    struct DropOtherTestEvent;
+
+    impl Middleware for DropOtherTestEvent {
+        fn before_publish(&self, event: &mut dyn Event) -> MiddlewareAction {
+            match event.event_name() {
+                "other_test_event" => MiddlewareAction::Stop,
+                _ => MiddlewareAction::Continue,
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_let_middlewares_short_circuit_publishing_and_handlers() {
+        let mut event_bus = SynchronousEventBus::new();
+        event_bus.add_middleware(SkipFailingHandler);
+        event_bus.add_middleware(DropOtherTestEvent);
+
+        let handler = Rc::new(TestEventHandler { total_messages_received: RefCell::new(0) });
+        event_bus.register(Rc::new(FailingHandler));
+        event_bus.register(handler.clone());
+
+        let report = event_bus.try_publish(TestEvent {}, FailurePolicy::FailFast);
+
+        assert!(report.is_success());
+        assert_eq!(report.outcomes.len(), 1);
+        assert_eq!(*handler.total_messages_received.borrow(), 1);
+    }
+
+    struct Auditor {
+        audited: RefCell<Vec<&'static str>>
+    }
+
+    impl AnySubscriber for Auditor {
+        fn handle_any(&self, event: &dyn Event) -> Result<(), SubscriberError> {
+            self.audited.borrow_mut().push(event.event_name());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_should_deliver_events_to_catch_all_and_pattern_subscribers() {
+        let event_bus = SynchronousEventBus::new();
+
+        let auditor = Rc::new(Auditor { audited: RefCell::new(vec![]) });
+        let other_auditor = Rc::new(Auditor { audited: RefCell::new(vec![]) });
+        event_bus.register_any(auditor.clone());
+        event_bus.register_pattern("other_*", other_auditor.clone());
+
+        event_bus.publish(TestEvent {});
+        event_bus.publish(OtherTestEvent {});
+
+        assert_eq!(*auditor.audited.borrow(), vec!["test_event", "other_test_event"]);
+        assert_eq!(*other_auditor.audited.borrow(), vec!["other_test_event"]);
+    }
+
+    #[test]
+    fn it_should_stop_delivering_events_once_unsubscribed() {
+        let event_bus = SynchronousEventBus::new();
+
+        let handler = Rc::new(TestEventHandler { total_messages_received: RefCell::new(0) });
+        let id = event_bus.register(handler.clone());
+        let guard = SubscriptionGuard::new(&event_bus, event_bus.register(handler.clone()));
+
+        event_bus.publish(TestEvent {});
+        drop(guard);
+        event_bus.publish(TestEvent {});
+
+        assert!(event_bus.unsubscribe(id));
+        assert!(!event_bus.unsubscribe(id));
+        event_bus.publish(TestEvent {});
+
+        assert_eq!(*handler.total_messages_received.borrow(), 3);
+    }
+
+    struct Journal {
+        entries: RefCell<Vec<&'static str>>
+    }
+
+    struct RaiseOtherTestEvent {
+        bus: std::rc::Weak<SynchronousEventBus>,
+        journal: Rc<Journal>
+    }
+
+    impl Subscriber<TestEvent> for RaiseOtherTestEvent {
+        fn handle_event(&self, _event: &TestEvent) -> Result<(), SubscriberError> {
+            self.journal.entries.borrow_mut().push("raised");
+            self.bus.upgrade().unwrap().publish(OtherTestEvent {});
+            Ok(())
+        }
+    }
+
+    impl Subscriber<TestEvent> for Journal {
+        fn handle_event(&self, _event: &TestEvent) -> Result<(), SubscriberError> {
+            self.entries.borrow_mut().push("test_event");
+            Ok(())
+        }
+    }
+
+    impl Subscriber<OtherTestEvent> for Journal {
+        fn handle_event(&self, _event: &OtherTestEvent) -> Result<(), SubscriberError> {
+            self.entries.borrow_mut().push("other_test_event");
+            Ok(())
+        }
+    }
+
+    fn journal_of(dispatch_mode: DispatchMode) -> Vec<&'static str> {
+        let event_bus = Rc::new(SynchronousEventBus::with_dispatch_mode(dispatch_mode));
+        let journal = Rc::new(Journal { entries: RefCell::new(vec![]) });
+
+        event_bus.register(Rc::new(RaiseOtherTestEvent { bus: Rc::downgrade(&event_bus), journal: journal.clone() }));
+        event_bus.register::<TestEvent, _>(journal.clone());
+        event_bus.register::<OtherTestEvent, _>(journal.clone());
+
+        event_bus.publish(TestEvent {});
+
+        journal.entries.take()
+    }
+
+    #[test]
+    fn it_should_handle_events_raised_by_subscribers_after_the_current_dispatch_when_deferred() {
+        assert_eq!(journal_of(DispatchMode::Immediate), vec!["raised", "other_test_event", "test_event"]);
+        assert_eq!(journal_of(DispatchMode::Deferred), vec!["raised", "test_event", "other_test_event"]);
+    }
+
+    struct Named {
+        name: &'static str,
+        journal: Rc<Journal>
+    }
+
+    impl Subscriber<TestEvent> for Named {
+        fn handle_event(&self, _event: &TestEvent) -> Result<(), SubscriberError> {
+            self.journal.entries.borrow_mut().push(self.name);
+            Ok(())
+        }
+    }
+
+    impl AnySubscriber for Named {
+        fn handle_any(&self, _event: &dyn Event) -> Result<(), SubscriberError> {
+            self.journal.entries.borrow_mut().push(self.name);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_should_run_subscribers_by_descending_priority() {
+        let event_bus = SynchronousEventBus::new();
+        let journal = Rc::new(Journal { entries: RefCell::new(vec![]) });
+        let named = |name| Rc::new(Named { name, journal: journal.clone() });
+
+        event_bus.register_pattern_with("test_*", named("auditor"), SubscriberOptions::default().with_priority(-1));
+        event_bus.register::<TestEvent, _>(named("notifier"));
+        event_bus.register_with::<TestEvent, _>(named("projection"), SubscriberOptions::default().with_priority(10));
+        event_bus.register::<TestEvent, _>(named("mailer"));
+
+        event_bus.publish(TestEvent {});
+
+        assert_eq!(*journal.entries.borrow(), vec!["projection", "notifier", "mailer", "auditor"]);
+    }
+}
@@ -0,0 +1,92 @@
+use std::any::{Any, type_name, TypeId};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::command::{AsyncCommandHandler, AsynchronousCommandBus, Command, CommandBusError};
+
+type HandlerClosure<C> = Box<dyn Fn(C) -> Pin<Box<dyn Future<Output = <C as Command>::Output> + Send>> + Send + Sync>;
+
+///
+/// An asynchronous command bus that runs the handler of every command in a tokio task.
+///
+#[derive(Default)]
+pub struct TokioCommandBus {
+    handlers: HashMap<TypeId, Box<dyn Any + Send + Sync>>
+}
+
+impl TokioCommandBus {
+    pub fn register<C, H>(&mut self, handler: Arc<H>) -> Result<(), CommandBusError>
+    where
+        C: Command,
+        H: AsyncCommandHandler<C>
+    {
+        match self.handlers.entry(TypeId::of::<C>()) {
+            Entry::Occupied(_) => Err(CommandBusError::HandlerAlreadyRegistered(type_name::<C>())),
+            Entry::Vacant(entry) => {
+                let closure: HandlerClosure<C> = Box::new(move |command| {
+                    let handler = handler.clone();
+
+                    Box::pin(async move {
+                        handler.handle(command).await
+                    })
+                });
+                entry.insert(Box::new(closure));
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl AsynchronousCommandBus for TokioCommandBus {
+    async fn dispatch<C: Command>(&self, command: C) -> Result<C::Output, CommandBusError> {
+        let handler = self.handlers
+                          .get(&TypeId::of::<C>())
+                          .and_then(|handler| handler.downcast_ref::<HandlerClosure<C>>())
+                          .ok_or(CommandBusError::HandlerNotFound(type_name::<C>()))?;
+
+        tokio::spawn(handler(command))
+            .await
+            .map_err(|e| {
+                log::error!("Error while processing command: {:?}", e);
+                CommandBusError::HandlerPanicked(type_name::<C>())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct PlaceOrder {
+        amount: u32
+    }
+
+    impl Command for PlaceOrder {
+        type Output = u32;
+    }
+
+    struct PlaceOrderHandler;
+
+    impl AsyncCommandHandler<PlaceOrder> for PlaceOrderHandler {
+        async fn handle(&self, command: PlaceOrder) -> u32 {
+            if command.amount == 0 {
+                panic!("Empty order");
+            }
+
+            command.amount * 2
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_dispatch_to_the_handler_and_report_panics() {
+        let mut command_bus = TokioCommandBus::default();
+        command_bus.register(Arc::new(PlaceOrderHandler)).unwrap();
+
+        assert_eq!(command_bus.dispatch(PlaceOrder { amount: 2 }).await.unwrap(), 4);
+        assert!(matches!(command_bus.dispatch(PlaceOrder { amount: 0 }).await, Err(CommandBusError::HandlerPanicked(_))));
+    }
+}
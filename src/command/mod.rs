@@ -0,0 +1,67 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+pub mod synchronous_command_bus;
+
+#[cfg(feature = "async")]
+pub mod asynchronous_command_bus;
+
+#[cfg(feature = "multithreading")]
+pub mod multithreading_command_bus;
+
+///
+/// A request to change the state of the system, handled by exactly one handler.
+///
+pub trait Command: Send + 'static {
+    type Output: Send + 'static;
+}
+
+pub trait CommandHandler<C: Command> {
+    fn handle(&self, command: C) -> C::Output;
+}
+
+#[cfg(feature = "async")]
+pub trait AsyncCommandHandler<C: Command>: Send + Sync + 'static {
+    fn handle(&self, command: C) -> impl std::future::Future<Output = C::Output> + Send;
+}
+
+pub trait CommandBus {
+    fn dispatch<C: Command>(&self, command: C) -> Result<C::Output, CommandBusError>;
+}
+
+#[cfg(feature = "async")]
+pub trait AsynchronousCommandBus {
+    fn dispatch<C: Command>(&self, command: C) -> impl std::future::Future<Output = Result<C::Output, CommandBusError>> + Send;
+}
+
+#[derive(Debug)]
+pub enum CommandBusError {
+    HandlerAlreadyRegistered(&'static str),
+    HandlerNotFound(&'static str),
+    HandlerPanicked(&'static str),
+}
+
+impl Display for CommandBusError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandBusError::HandlerAlreadyRegistered(command) => write!(f, "A handler is already registered for {}", command),
+            CommandBusError::HandlerNotFound(command) => write!(f, "No handler registered for {}", command),
+            CommandBusError::HandlerPanicked(command) => write!(f, "The handler of {} panicked", command),
+        }
+    }
+}
+
+impl Error for CommandBusError {}
+
+#[macro_export]
+macro_rules! impl_command_handler {
+    ($struct_name:ident, $method_name:ident, $($command:ident), *) => {
+        $(
+            impl $crate::command::CommandHandler<$command> for $struct_name {
+                fn handle(&self, command: $command) -> <$command as $crate::command::Command>::Output {
+                    self.$method_name(command)
+                }
+            }
+        )*
+    };
+}
@@ -0,0 +1,120 @@
+use std::any::{Any, type_name, TypeId};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::Arc;
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::bus::multithreading_bus::MultithreadingEventBusError;
+use crate::command::{Command, CommandBus, CommandBusError, CommandHandler};
+
+type HandlerClosure<C> = Arc<dyn Fn(C) -> <C as Command>::Output + Send + Sync>;
+
+///
+/// A command bus that runs the handler of every command in a thread pool,
+/// blocking the caller until the handler returns.
+///
+pub struct MultithreadingCommandBus {
+    handlers: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    thread_pool: ThreadPool,
+}
+
+impl Default for MultithreadingCommandBus {
+    fn default() -> Self {
+        Self {
+            handlers: HashMap::default(),
+            thread_pool: ThreadPoolBuilder::new().build().expect("Error creating thread pool"),
+        }
+    }
+}
+
+impl MultithreadingCommandBus {
+    ///
+    /// Create a new MultithreadingCommandBus with a given thread pool.
+    ///
+    pub fn new(thread_pool: ThreadPool) -> Self {
+        Self {
+            thread_pool,
+            handlers: HashMap::default(),
+        }
+    }
+
+    ///
+    /// Create a new MultithreadingCommandBus with a given number of threads.
+    ///
+    pub fn with_num_threads(threads: usize) -> Result<Self, MultithreadingEventBusError> {
+        let thread_pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|error| MultithreadingEventBusError::ThreadPoolError(format!("Error creating thread pool: {}", error)))?;
+
+        Ok(Self::new(thread_pool))
+    }
+
+    ///
+    /// Register the handler of a given command type.
+    ///
+    pub fn register<C, H>(&mut self, handler: Arc<H>) -> Result<(), CommandBusError>
+    where
+        C: Command,
+        H: CommandHandler<C> + Send + Sync + 'static
+    {
+        match self.handlers.entry(TypeId::of::<C>()) {
+            Entry::Occupied(_) => Err(CommandBusError::HandlerAlreadyRegistered(type_name::<C>())),
+            Entry::Vacant(entry) => {
+                let closure: HandlerClosure<C> = Arc::new(move |command| handler.handle(command));
+                entry.insert(Box::new(closure));
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl CommandBus for MultithreadingCommandBus {
+    fn dispatch<C: Command>(&self, command: C) -> Result<C::Output, CommandBusError> {
+        let handler = self.handlers
+                          .get(&TypeId::of::<C>())
+                          .and_then(|handler| handler.downcast_ref::<HandlerClosure<C>>())
+                          .ok_or(CommandBusError::HandlerNotFound(type_name::<C>()))?
+                          .clone();
+
+        self.thread_pool
+            .install(move || catch_unwind(AssertUnwindSafe(|| handler(command))))
+            .map_err(|_| CommandBusError::HandlerPanicked(type_name::<C>()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::impl_command_handler;
+
+    use super::*;
+
+    struct CalculateTotal {
+        prices: Vec<u32>
+    }
+
+    impl Command for CalculateTotal {
+        type Output = u32;
+    }
+
+    struct CalculateTotalHandler;
+
+    impl CalculateTotalHandler {
+        fn on(&self, command: CalculateTotal) -> u32 {
+            command.prices.iter().sum()
+        }
+    }
+
+    impl_command_handler!(CalculateTotalHandler, on, CalculateTotal);
+
+    #[test]
+    fn it_should_dispatch_in_the_thread_pool_and_return_the_output() {
+        let mut command_bus = MultithreadingCommandBus::with_num_threads(2).unwrap();
+        command_bus.register(Arc::new(CalculateTotalHandler)).unwrap();
+
+        assert_eq!(command_bus.dispatch(CalculateTotal { prices: vec![1, 2, 3] }).unwrap(), 6);
+    }
+}
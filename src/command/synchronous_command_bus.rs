@@ -0,0 +1,111 @@
+use std::any::{Any, type_name, TypeId};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::command::{Command, CommandBus, CommandBusError, CommandHandler};
+
+type HandlerClosure<C> = Box<dyn Fn(C) -> <C as Command>::Output>;
+
+///
+/// A synchronous command bus that dispatches every command to its only handler.
+///
+#[derive(Default)]
+pub struct SynchronousCommandBus {
+    handlers: HashMap<TypeId, Box<dyn Any>>
+}
+
+impl SynchronousCommandBus {
+    pub fn new() -> Self {
+        SynchronousCommandBus {
+            handlers: HashMap::new()
+        }
+    }
+
+    pub fn register<C, H>(&mut self, handler: Rc<H>) -> Result<(), CommandBusError>
+    where
+        C: Command,
+        H: CommandHandler<C> + 'static
+    {
+        match self.handlers.entry(TypeId::of::<C>()) {
+            Entry::Occupied(_) => Err(CommandBusError::HandlerAlreadyRegistered(type_name::<C>())),
+            Entry::Vacant(entry) => {
+                let closure: HandlerClosure<C> = Box::new(move |command| handler.handle(command));
+                entry.insert(Box::new(closure));
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl CommandBus for SynchronousCommandBus {
+    fn dispatch<C: Command>(&self, command: C) -> Result<C::Output, CommandBusError> {
+        let handler = self.handlers
+                          .get(&TypeId::of::<C>())
+                          .and_then(|handler| handler.downcast_ref::<HandlerClosure<C>>())
+                          .ok_or(CommandBusError::HandlerNotFound(type_name::<C>()))?;
+
+        Ok(handler(command))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use crate::impl_command_handler;
+
+    use super::*;
+
+    struct OpenAccount {
+        owner: String
+    }
+
+    impl Command for OpenAccount {
+        type Output = Result<u32, String>;
+    }
+
+    struct CloseAccount;
+
+    impl Command for CloseAccount {
+        type Output = ();
+    }
+
+    struct AccountHandler {
+        opened: RefCell<Vec<String>>
+    }
+
+    impl AccountHandler {
+        fn open_account(&self, command: OpenAccount) -> Result<u32, String> {
+            if command.owner.is_empty() {
+                return Err("Owner is required".to_string());
+            }
+
+            self.opened.borrow_mut().push(command.owner);
+            Ok(self.opened.borrow().len() as u32)
+        }
+    }
+
+    impl_command_handler!(AccountHandler, open_account, OpenAccount);
+
+    #[test]
+    fn it_should_dispatch_to_the_handler_and_return_its_output() {
+        let mut command_bus = SynchronousCommandBus::new();
+        command_bus.register(Rc::new(AccountHandler { opened: RefCell::new(vec![]) })).unwrap();
+
+        assert_eq!(command_bus.dispatch(OpenAccount { owner: "owner".to_string() }).unwrap(), Ok(1));
+        assert_eq!(command_bus.dispatch(OpenAccount { owner: "".to_string() }).unwrap(), Err("Owner is required".to_string()));
+    }
+
+    #[test]
+    fn it_should_fail_without_handler_or_with_two_handlers() {
+        let mut command_bus = SynchronousCommandBus::new();
+        let handler = Rc::new(AccountHandler { opened: RefCell::new(vec![]) });
+
+        command_bus.register::<OpenAccount, _>(handler.clone()).unwrap();
+
+        assert!(matches!(command_bus.register::<OpenAccount, _>(handler), Err(CommandBusError::HandlerAlreadyRegistered(_))));
+        assert!(matches!(command_bus.dispatch(CloseAccount), Err(CommandBusError::HandlerNotFound(_))));
+    }
+}
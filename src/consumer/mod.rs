@@ -4,8 +4,17 @@ use serde::Serialize;
 use crate::serializer::deserialized_event::EventDeserializable;
 use crate::subscriber::SubscriberError;
 
+#[cfg(feature = "rabbit")]
 pub mod rabbitmq_consumer;
+#[cfg(feature = "rabbit")]
 pub mod rabbitmq_retryer;
+#[cfg(feature = "rabbit")]
+pub mod rabbitmq_rpc_server;
+
+#[cfg(feature = "postgres")]
+pub mod postgres_consumer;
+#[cfg(feature = "postgres")]
+pub mod postgres_retryer;
 
 #[allow(async_fn_in_trait)]
 pub trait AsyncConsumer {
@@ -0,0 +1,171 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::error;
+use serde_json::Value;
+use tokio_postgres::Client;
+
+use crate::consumer::{AsyncConsumer, PayloadHandler};
+use crate::consumer::postgres_retryer::{PostgresRetryer, Settlement};
+use crate::postgres::postgres_listener::PostgresListener;
+use crate::postgres::PostgresError;
+use crate::serializer::EventDeserializer;
+
+const BATCH_SIZE: i64 = 10;
+const LEASE_DURATION: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+struct LeasedEvent {
+    id: i64,
+    payload: Vec<u8>,
+    attempts: i32,
+    requeues: i32,
+}
+
+///
+/// Consumes the events stored for a queue by the `PostgresEventBus`.
+///
+/// Rows are leased with `FOR UPDATE SKIP LOCKED`, so several consumers can share a queue.
+/// A lease that is not settled before it expires makes the event available again.
+///
+pub struct PostgresConsumer<'a, D: EventDeserializer, EH: PayloadHandler<Value>> {
+    client: Arc<Client>,
+    listener: PostgresListener,
+    table: String,
+    queue: String,
+    deserializer: &'a D,
+    handler: EH,
+    retryer: &'a PostgresRetryer,
+}
+
+impl<'a, D: EventDeserializer, EH: PayloadHandler<Value>> PostgresConsumer<'a, D, EH> {
+    pub fn new(
+        client: Arc<Client>,
+        listener: PostgresListener,
+        table: &'a str,
+        queue: &'a str,
+        deserializer: &'a D,
+        handler: EH,
+        retryer: &'a PostgresRetryer
+    ) -> Self {
+        Self {
+            client,
+            listener,
+            table: table.to_string(),
+            queue: queue.to_string(),
+            deserializer,
+            handler,
+            retryer
+        }
+    }
+
+    async fn lease_events(&self) -> Result<Vec<LeasedEvent>, PostgresError> {
+        let statement = format!(
+            "UPDATE {table} SET status = 'processing', attempts = attempts + 1, locked_until = now() + make_interval(secs => $3)
+            WHERE id IN (
+                SELECT id FROM {table}
+                WHERE queue = $1 AND (
+                    (status = 'pending' AND available_at <= now())
+                    OR (status = 'processing' AND locked_until < now())
+                )
+                ORDER BY id
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, payload, attempts, requeues",
+            table = self.table
+        );
+
+        let rows = self.client
+            .query(statement.as_str(), &[&self.queue, &BATCH_SIZE, &LEASE_DURATION.as_secs_f64()])
+            .await
+            .map_err(PostgresError::CannotLeaseEvents)?;
+
+        let mut events: Vec<LeasedEvent> = rows.iter()
+            .map(|row| LeasedEvent {
+                id: row.get(0),
+                payload: row.get(1),
+                attempts: row.get(2),
+                requeues: row.get(3),
+            })
+            .collect();
+
+        events.sort_by_key(|event| event.id);
+
+        Ok(events)
+    }
+
+    async fn wait_for_events(&mut self) {
+        let queue = self.queue.as_str();
+        let listener = &mut self.listener;
+
+        let _ = tokio::time::timeout(POLL_INTERVAL, async {
+            while let Some(notification) = listener.recv().await {
+                if notification.payload() == queue {
+                    return;
+                }
+            }
+
+            std::future::pending::<()>().await
+        }).await;
+    }
+
+    ///
+    /// Handle the event and settle it. An event that cannot be settled is leased again once its lease expires.
+    ///
+    async fn process(&mut self, event: LeasedEvent) {
+        let (settlement, error) = match self.deserializer.deserialize::<Value>(&event.payload) {
+            Ok(event_deserializable) => {
+                let result = self.handler.handle_value_payload(&event_deserializable).await;
+                let settlement = self.retryer.settlement(&result, event.attempts, event.requeues);
+
+                (settlement, result.err().map(|e| e.to_string()).unwrap_or_default())
+            },
+            Err(e) => {
+                error!("Failed to deserialize event {}: {}", event.id, e);
+                (Settlement::DeadLetter, e.to_string())
+            }
+        };
+
+        if let Err(e) = PostgresRetryer::settle(&self.client, &self.table, event.id, settlement, error.as_str()).await {
+            error!("Failed to settle event {}: {}", event.id, e);
+        }
+    }
+}
+
+impl<'a, D: EventDeserializer, EH: PayloadHandler<Value>> AsyncConsumer for PostgresConsumer<'a, D, EH> {
+    ///
+    /// Consume the queue forever. Database errors are logged and the events polled again, relying
+    /// on notifications only once listening succeeds.
+    ///
+    async fn consume(&mut self) {
+        let listen = self.client
+            .batch_execute(format!("LISTEN \"{}\"", self.table).as_str())
+            .await
+            .map_err(PostgresError::CannotListen);
+
+        if let Err(e) = listen {
+            error!("Failed to listen for events, polling every {:?}: {}", POLL_INTERVAL, e);
+        }
+
+        loop {
+            let events = match self.lease_events().await {
+                Ok(events) => events,
+                Err(e) => {
+                    error!("Failed to lease events: {}", e);
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                    continue;
+                }
+            };
+
+            if events.is_empty() {
+                self.wait_for_events().await;
+                continue;
+            }
+
+            for event in events {
+                self.process(event).await;
+            }
+        }
+    }
+}
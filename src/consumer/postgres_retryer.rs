@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use tokio_postgres::Client;
+
+use crate::postgres::PostgresError;
+use crate::subscriber::SubscriberError;
+
+///
+/// How long a requeued event waits before it is leased again.
+///
+pub const REQUEUE_DELAY: Duration = Duration::from_secs(1);
+
+///
+/// What becomes of a leased event once its subscriber is done with it.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Settlement {
+    ///
+    /// Delete the event, handled or discarded.
+    ///
+    Acknowledge,
+    ///
+    /// Make the event available again after the delay.
+    ///
+    Retry(Duration),
+    ///
+    /// Make the event available again after the delay, counting a requeue instead of an attempt.
+    ///
+    Requeue(Duration),
+    ///
+    /// Keep the event in the `dead_letter` state.
+    ///
+    DeadLetter,
+}
+
+impl Settlement {
+    ///
+    /// The statement settling the event, taking its id as `$1`, the error as `$2` and the delay in seconds as `$3`.
+    ///
+    pub fn statement(&self, table: &str) -> String {
+        match self {
+            Settlement::Acknowledge => format!("DELETE FROM {} WHERE id = $1", table),
+            Settlement::Retry(_) => format!(
+                "UPDATE {} SET status = 'pending', locked_until = NULL, last_error = $2, available_at = now() + make_interval(secs => $3) WHERE id = $1",
+                table
+            ),
+            Settlement::Requeue(_) => format!(
+                "UPDATE {} SET status = 'pending', locked_until = NULL, last_error = $2, attempts = attempts - 1, requeues = requeues + 1, available_at = now() + make_interval(secs => $3) WHERE id = $1",
+                table
+            ),
+            Settlement::DeadLetter => format!(
+                "UPDATE {} SET status = 'dead_letter', locked_until = NULL, last_error = $2 WHERE id = $1",
+                table
+            ),
+        }
+    }
+}
+
+pub struct PostgresRetryer {
+    pub max_retries: u32,
+    pub max_requeues: u32,
+    retry_delay: Duration,
+}
+
+impl PostgresRetryer {
+    pub fn new(max_retries: u32, retry_delay: Duration) -> Self {
+        PostgresRetryer { max_retries, max_requeues: 3, retry_delay }
+    }
+
+    ///
+    /// Requeue an event up to `max_requeues` times before dead-lettering it.
+    ///
+    pub fn with_max_requeues(mut self, max_requeues: u32) -> Self {
+        self.max_requeues = max_requeues;
+        self
+    }
+
+    ///
+    /// How to settle an event attempted `attempts` times and requeued `requeues` times, given the result of its subscriber.
+    ///
+    pub fn settlement(&self, result: &Result<(), SubscriberError>, attempts: i32, requeues: i32) -> Settlement {
+        match result {
+            Ok(_) | Err(SubscriberError::UnrecoverableError) => Settlement::Acknowledge,
+            Err(SubscriberError::Requeue) if requeues as i64 >= self.max_requeues as i64 => Settlement::DeadLetter,
+            Err(SubscriberError::Requeue) => Settlement::Requeue(REQUEUE_DELAY),
+            Err(e) if e.is_retryable() => self.retry_settlement(attempts, e.delay()),
+            Err(_) => Settlement::DeadLetter,
+        }
+    }
+
+    ///
+    /// Makes the event available again after the retry delay, or moves it to the
+    /// `dead_letter` state once it has been attempted more than `max_retries` times.
+    ///
+    pub async fn retry(&self, client: &Client, table: &str, id: i64, attempts: i32, error: &str) -> Result<(), PostgresError> {
+        self.retry_after(client, table, id, attempts, error, None).await
+    }
+
+    ///
+    /// Like `retry`, waiting for the delay instead of the retry delay when there is one.
+    ///
+    pub async fn retry_after(&self, client: &Client, table: &str, id: i64, attempts: i32, error: &str, delay: Option<Duration>) -> Result<(), PostgresError> {
+        Self::settle(client, table, id, self.retry_settlement(attempts, delay), error).await
+    }
+
+    ///
+    /// Makes the event available again after the requeue delay, without counting the attempt.
+    ///
+    pub async fn requeue(client: &Client, table: &str, id: i64) -> Result<(), PostgresError> {
+        Self::settle(client, table, id, Settlement::Requeue(REQUEUE_DELAY), "Requeued").await
+    }
+
+    pub async fn dead_letter(client: &Client, table: &str, id: i64, error: &str) -> Result<(), PostgresError> {
+        Self::settle(client, table, id, Settlement::DeadLetter, error).await
+    }
+
+    pub async fn settle(client: &Client, table: &str, id: i64, settlement: Settlement, error: &str) -> Result<(), PostgresError> {
+        let statement = settlement.statement(table);
+
+        let result = match settlement {
+            Settlement::Acknowledge => client.execute(statement.as_str(), &[&id]).await,
+            Settlement::Retry(delay) | Settlement::Requeue(delay) => client.execute(statement.as_str(), &[&id, &error, &delay.as_secs_f64()]).await,
+            Settlement::DeadLetter => client.execute(statement.as_str(), &[&id, &error]).await,
+        };
+
+        result.map_err(PostgresError::CannotUpdateEvent)?;
+
+        Ok(())
+    }
+
+    fn retry_settlement(&self, attempts: i32, delay: Option<Duration>) -> Settlement {
+        if attempts as i64 > self.max_retries as i64 {
+            return Settlement::DeadLetter;
+        }
+
+        Settlement::Retry(delay.unwrap_or(self.retry_delay))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_retry_until_out_of_retries_then_dead_letter() {
+        let retryer = PostgresRetryer::new(2, Duration::from_secs(5));
+        let failed = Err(SubscriberError::Inner("Timeout".into()));
+
+        assert_eq!(retryer.settlement(&failed, 1, 0), Settlement::Retry(Duration::from_secs(5)));
+        assert_eq!(retryer.settlement(&failed, 2, 0), Settlement::Retry(Duration::from_secs(5)));
+        assert_eq!(retryer.settlement(&failed, 3, 0), Settlement::DeadLetter);
+    }
+
+    #[test]
+    fn it_should_retry_after_the_delay_asked_by_the_error() {
+        let retryer = PostgresRetryer::new(2, Duration::from_secs(5));
+        let rate_limited = Err(SubscriberError::retryable("Rate limited").with_delay(Duration::from_secs(30)));
+
+        assert_eq!(retryer.settlement(&rate_limited, 1, 0), Settlement::Retry(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn it_should_settle_handled_discarded_requeued_and_permanent_failures() {
+        let retryer = PostgresRetryer::new(2, Duration::from_secs(5));
+
+        assert_eq!(retryer.settlement(&Ok(()), 1, 0), Settlement::Acknowledge);
+        assert_eq!(retryer.settlement(&Err(SubscriberError::UnrecoverableError), 1, 0), Settlement::Acknowledge);
+        assert_eq!(retryer.settlement(&Err(SubscriberError::Requeue), 3, 0), Settlement::Requeue(REQUEUE_DELAY));
+        assert_eq!(retryer.settlement(&Err(SubscriberError::permanent("Invalid event")), 1, 0), Settlement::DeadLetter);
+    }
+
+    #[test]
+    fn it_should_dead_letter_an_event_once_out_of_requeues() {
+        let retryer = PostgresRetryer::new(2, Duration::from_secs(5)).with_max_requeues(2);
+        let requeued = Err(SubscriberError::Requeue);
+
+        assert_eq!(retryer.settlement(&requeued, 1, 0), Settlement::Requeue(REQUEUE_DELAY));
+        assert_eq!(retryer.settlement(&requeued, 1, 1), Settlement::Requeue(REQUEUE_DELAY));
+        assert_eq!(retryer.settlement(&requeued, 1, 2), Settlement::DeadLetter);
+    }
+
+    #[test]
+    fn it_should_build_the_statement_of_every_settlement() {
+        assert_eq!(Settlement::Acknowledge.statement("events"), "DELETE FROM events WHERE id = $1");
+        assert!(Settlement::Retry(Duration::from_secs(5)).statement("events").starts_with("UPDATE events SET status = 'pending', locked_until = NULL, last_error = $2"));
+        assert!(Settlement::Requeue(REQUEUE_DELAY).statement("events").contains("attempts = attempts - 1, requeues = requeues + 1"));
+        assert!(Settlement::DeadLetter.statement("events").contains("status = 'dead_letter'"));
+    }
+}
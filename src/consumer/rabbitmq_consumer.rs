@@ -60,35 +60,28 @@ impl<'a, D: EventDeserializer, EH: PayloadHandler<Value>> AsyncConsumer for Rabb
 
         while let Some(delivery) = consumer.next().await {
             if let Ok(delivery) = delivery {
-                let payload = std::str::from_utf8(&delivery.data).unwrap();
-
                 let event_deserializable = self.deserializer
-                                               .deserialize::<Value>(payload.to_string());
+                                               .deserialize_amqp::<Value>(&delivery.data, &delivery.properties);
 
-                if event_deserializable.is_err() {
-                    error!("Failed to deserialize event {}", payload);
+                if let Err(e) = &event_deserializable {
+                    error!("Failed to deserialize event {}: {}", String::from_utf8_lossy(&delivery.data), e);
                     continue;
                 }
 
                 let result = self.handler.handle_value_payload(&event_deserializable.expect("Failed to deserialize event")).await;
 
-                match result {
-                    Ok(_) | Err(SubscriberError::UnrecoverableError) => {
-                        channel.basic_ack(delivery.delivery_tag, BasicAckOptions::default())
-                               .await
-                            .expect("Failed to acknowledge message");
-                    },
-                    Err(SubscriberError::Inner(_)) => {
-                        self.retryer
-                            .retry(&delivery, self.queue.as_str())
-                            .await
-                            .expect("Failed to retry message");
+                let settled = match result {
+                    Ok(_) | Err(SubscriberError::UnrecoverableError) => Ok(()),
+                    Err(SubscriberError::Requeue) => self.retryer.requeue(&delivery, self.queue.as_str()).await,
+                    Err(e) if e.is_retryable() => self.retryer.retry_after(&delivery, self.queue.as_str(), e.delay()).await,
+                    Err(_) => self.retryer.dead_letter(&delivery, self.queue.as_str()).await,
+                };
 
-                        channel.basic_ack(delivery.delivery_tag, BasicAckOptions::default())
-                               .await
-                            .expect("Failed to acknowledge message");
-                    }
-                }
+                settled.expect("Failed to retry message");
+
+                channel.basic_ack(delivery.delivery_tag, BasicAckOptions::default())
+                       .await
+                    .expect("Failed to acknowledge message");
             }
         }
     }
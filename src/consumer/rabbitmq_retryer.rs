@@ -1,34 +1,115 @@
 use std::sync::Arc;
+use std::time::Duration;
 
+use lapin::BasicProperties;
 use lapin::message::Delivery;
 use lapin::types::FieldTable;
+use log::warn;
 
 use crate::bus::error::PublishError;
 use crate::rabbit::rabbit_publisher::RabbitPublisher;
 
+///
+/// The header counting how many times a message was requeued.
+///
+pub const REQUEUE_COUNT_HEADER: &str = "requeue_count";
+
 pub struct RabbitMQRetryer {
     pub max_retries: u32,
+    pub max_requeues: u32,
     publisher: Arc<RabbitPublisher>,
 }
 
 impl RabbitMQRetryer {
     pub fn new(publisher: Arc<RabbitPublisher>, max_retries: u32) -> Self {
-        RabbitMQRetryer { publisher, max_retries }
+        RabbitMQRetryer { publisher, max_retries, max_requeues: 3 }
+    }
+
+    ///
+    /// Requeue a message up to `max_requeues` times before dead-lettering it.
+    ///
+    pub fn with_max_requeues(mut self, max_requeues: u32) -> Self {
+        self.max_requeues = max_requeues;
+        self
     }
 
     pub async fn retry(&self, delivery: &Delivery, queue_name: &str) -> Result<(), PublishError> {
+        self.retry_after(delivery, queue_name, None).await
+    }
+
+    ///
+    /// Retry the delivery through the retry exchange, expiring it after the delay instead of the
+    /// TTL of the retry queue when there is one. The TTL of the queue still caps the delay.
+    ///
+    pub async fn retry_after(&self, delivery: &Delivery, queue_name: &str, delay: Option<Duration>) -> Result<(), PublishError> {
         let redelivery_count = Self::get_redelivery_count(delivery);
         let exchange = self.get_target_exchange(delivery, redelivery_count);
         let headers = Self::add_redelivery_count_header(delivery, redelivery_count);
 
-        self.publisher.publish_with_headers(
+        let mut properties = delivery.properties.clone().with_headers(headers);
+        if let Some(delay) = delay {
+            properties = properties.with_expiration(delay.as_millis().to_string().into());
+        }
+
+        self.publisher.publish_with_properties(
             &delivery.data,
             queue_name,
             &exchange,
-            headers
+            properties
+        ).await
+    }
+
+    ///
+    /// Deliver the message to the queue again, without counting it as a redelivery, or dead-letter
+    /// it once it has been requeued `max_requeues` times.
+    ///
+    pub async fn requeue(&self, delivery: &Delivery, queue_name: &str) -> Result<(), PublishError> {
+        let Some(properties) = Self::requeued_properties(&delivery.properties, self.max_requeues) else {
+            warn!("Dead-lettering a message of {} requeued {} times", queue_name, self.max_requeues);
+            return self.dead_letter(delivery, queue_name).await;
+        };
+
+        self.publisher.publish_with_properties(
+            &delivery.data,
+            queue_name,
+            delivery.exchange.as_str(),
+            properties
+        ).await
+    }
+
+    ///
+    /// Move the message to the dead letter queue right away.
+    ///
+    pub async fn dead_letter(&self, delivery: &Delivery, queue_name: &str) -> Result<(), PublishError> {
+        self.publisher.publish_with_properties(
+            &delivery.data,
+            queue_name,
+            &format!("dead_letter-{}", delivery.exchange),
+            delivery.properties.clone()
         ).await
     }
 
+    ///
+    /// The properties of the requeued message with its requeue count incremented, or None once
+    /// it has been requeued `max_requeues` times.
+    ///
+    fn requeued_properties(properties: &BasicProperties, max_requeues: u32) -> Option<BasicProperties> {
+        let requeue_count = properties.headers()
+                                      .as_ref()
+                                      .and_then(|headers| headers.inner().get(REQUEUE_COUNT_HEADER))
+                                      .and_then(|count| count.as_long_long_int())
+                                      .unwrap_or(0);
+
+        if requeue_count >= max_requeues as i64 {
+            return None;
+        }
+
+        let mut headers = properties.headers().clone().unwrap_or_default();
+        headers.insert(REQUEUE_COUNT_HEADER.into(), (requeue_count + 1).into());
+
+        Some(properties.clone().with_headers(headers))
+    }
+
     fn get_target_exchange(&self, delivery: &Delivery, redelivery_count: i64) -> String {
         if redelivery_count > self.max_retries as i64 {
             return format!("dead_letter-{}", delivery.exchange);
@@ -59,4 +140,23 @@ impl RabbitMQRetryer {
         redelivery_count += 1;
         redelivery_count
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use lapin::types::AMQPValue;
+
+    use super::*;
+
+    #[test]
+    fn it_should_count_the_requeues_until_the_max_requeues() {
+        let properties = BasicProperties::default();
+
+        let properties = RabbitMQRetryer::requeued_properties(&properties, 2).unwrap();
+        assert_eq!(properties.headers().as_ref().unwrap().inner().get(REQUEUE_COUNT_HEADER), Some(&AMQPValue::LongLongInt(1)));
+
+        let properties = RabbitMQRetryer::requeued_properties(&properties, 2).unwrap();
+        assert_eq!(properties.headers().as_ref().unwrap().inner().get(REQUEUE_COUNT_HEADER), Some(&AMQPValue::LongLongInt(2)));
+
+        assert!(RabbitMQRetryer::requeued_properties(&properties, 2).is_none());
+    }
+}
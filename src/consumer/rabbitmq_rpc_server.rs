@@ -0,0 +1,207 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use futures_lite::stream::StreamExt;
+use lapin::{BasicProperties, Connection};
+use lapin::message::Delivery;
+use lapin::options::{BasicAckOptions, BasicConsumeOptions};
+use lapin::types::{AMQPValue, FieldTable, LongString, ShortString};
+use log::error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::consumer::AsyncConsumer;
+use crate::event::{Event, EventWithMetadata};
+use crate::rabbit::RPC_ERROR_HEADER;
+use crate::rabbit::rabbit_channel::RabbitChannel;
+use crate::rabbit::rabbit_publisher::RabbitPublisher;
+use crate::serializer::{EventDeserializer, EventSerializer};
+use crate::subscriber::SubscriberError;
+
+#[allow(async_fn_in_trait)]
+pub trait RpcHandler<Req> {
+    type Response: Event + EventWithMetadata + Serialize;
+
+    async fn handle(&mut self, request: Req) -> Result<Self::Response, SubscriberError>;
+}
+
+///
+/// Consumes requests from a queue and publishes the response of the handler to their `reply_to` queue.
+///
+/// When the request cannot be answered, the reply carries the reason in the `x-rpc-error` header.
+///
+pub struct RabbitMQRpcServer<'a, Req, S: EventSerializer, D: EventDeserializer, H: RpcHandler<Req>> {
+    channel: RabbitChannel,
+    queue: String,
+    consumer_tag: String,
+    serializer: &'a S,
+    deserializer: &'a D,
+    publisher: &'a RabbitPublisher,
+    handler: H,
+    request: PhantomData<Req>,
+}
+
+impl<'a, Req, S, D, H> RabbitMQRpcServer<'a, Req, S, D, H>
+where
+    Req: Serialize + DeserializeOwned,
+    S: EventSerializer,
+    D: EventDeserializer,
+    H: RpcHandler<Req>
+{
+    pub async fn new(
+        connection: Arc<Connection>,
+        queue: &'a str,
+        consumer_tag: &'a str,
+        serializer: &'a S,
+        deserializer: &'a D,
+        publisher: &'a RabbitPublisher,
+        handler: H
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let channel = connection.create_channel().await?;
+
+        Ok(
+            Self {
+                channel: RabbitChannel::new(connection, channel),
+                queue: queue.to_string(),
+                consumer_tag: consumer_tag.to_string(),
+                serializer,
+                deserializer,
+                publisher,
+                handler,
+                request: PhantomData,
+            }
+        )
+    }
+
+    async fn answer(&mut self, delivery: &Delivery) -> Result<Vec<u8>, String> {
+        let request = self.deserializer
+                          .deserialize::<Req>(&delivery.data)
+                          .map_err(|e| e.to_string())?;
+
+        let response = self.handler
+                           .handle(request.data.attributes)
+                           .await
+                           .map_err(|e| e.to_string())?;
+
+        self.serializer
+            .serialize(&response)
+            .map_err(|e| e.to_string())
+    }
+
+    async fn reply(&self, delivery: &Delivery, answer: Result<Vec<u8>, String>) {
+        let Some(reply_to) = delivery.properties.reply_to() else {
+            error!("Received a request without reply_to in queue {}", self.queue);
+            return;
+        };
+
+        if let Err(message) = &answer {
+            error!("Error while processing request: {}", message);
+        }
+
+        let (payload, properties) = reply_message(&delivery.properties, self.serializer.content_type(), answer);
+
+        if self.publisher.publish_with_properties(&payload, reply_to.as_str(), "", properties).await.is_err() {
+            error!("Failed to publish reply to {}", reply_to);
+        }
+    }
+}
+
+///
+/// The payload and properties of the reply to a request, correlated with it.
+///
+fn reply_message(request: &BasicProperties, content_type: &str, answer: Result<Vec<u8>, String>) -> (Vec<u8>, BasicProperties) {
+    let mut properties = BasicProperties::default().with_content_type(content_type.into());
+    if let Some(correlation_id) = request.correlation_id() {
+        properties = properties.with_correlation_id(correlation_id.clone());
+    }
+
+    match answer {
+        Ok(response) => (response, properties),
+        Err(message) => {
+            let mut headers = FieldTable::default();
+            headers.insert(ShortString::from(RPC_ERROR_HEADER), AMQPValue::LongString(LongString::from(message)));
+
+            (vec![], properties.with_headers(headers))
+        }
+    }
+}
+
+impl<'a, Req, S, D, H> AsyncConsumer for RabbitMQRpcServer<'a, Req, S, D, H>
+where
+    Req: Serialize + DeserializeOwned,
+    S: EventSerializer,
+    D: EventDeserializer,
+    H: RpcHandler<Req>
+{
+    ///
+    /// Answer the requests of the queue until the consumer is cancelled, or return right away when
+    /// the queue cannot be consumed.
+    ///
+    async fn consume(&mut self) {
+        let channel = match self.channel.get_guard_channel().await {
+            Ok(channel) => channel,
+            Err(e) => {
+                error!("Failed to open a channel for queue {}: {}", self.queue, e);
+                return;
+            }
+        };
+
+        let consumer = channel
+            .basic_consume(
+                &self.queue,
+                &self.consumer_tag,
+                BasicConsumeOptions::default(),
+                FieldTable::default(),
+            )
+            .await;
+        drop(channel);
+
+        let mut consumer = match consumer {
+            Ok(consumer) => consumer,
+            Err(e) => {
+                error!("Failed to consume queue {}: {}", self.queue, e);
+                return;
+            }
+        };
+
+        while let Some(delivery) = consumer.next().await {
+            if let Ok(delivery) = delivery {
+                let answer = self.answer(&delivery).await;
+                self.reply(&delivery, answer).await;
+
+                if let Err(e) = delivery.ack(BasicAckOptions::default()).await {
+                    error!("Failed to acknowledge request: {}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_correlate_the_reply_with_its_request() {
+        let request = BasicProperties::default().with_correlation_id("42".into());
+
+        let (payload, properties) = reply_message(&request, "application/json", Ok(b"{}".to_vec()));
+
+        assert_eq!(payload, b"{}");
+        assert_eq!(properties.correlation_id().as_ref().unwrap().as_str(), "42");
+        assert_eq!(properties.content_type().as_ref().unwrap().as_str(), "application/json");
+        assert!(properties.headers().is_none());
+    }
+
+    #[test]
+    fn it_should_reply_with_the_error_header_when_the_request_cannot_be_answered() {
+        let request = BasicProperties::default().with_correlation_id("42".into());
+
+        let (payload, properties) = reply_message(&request, "application/json", Err("Unknown account".to_string()));
+
+        let headers = properties.headers().as_ref().unwrap();
+        assert!(payload.is_empty());
+        assert_eq!(properties.correlation_id().as_ref().unwrap().as_str(), "42");
+        assert_eq!(headers.inner().get(RPC_ERROR_HEADER), Some(&AMQPValue::LongString(LongString::from("Unknown account"))));
+    }
+}
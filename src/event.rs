@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use downcaster::AsAny;
 #[cfg(feature = "serializer")]
@@ -10,6 +12,52 @@ pub trait Event: AsAny + Sync + Send + 'static {
     fn event_version(&self) -> &'static str {
         "1.0"
     }
+
+    ///
+    /// Mutable access to the metadata of the event, used by middlewares to stamp it on the in-process buses.
+    /// The derive and the `event!` macro fill it in; the Rabbit and Postgres buses use `EventWithMetadata` instead.
+    ///
+    fn metadata_mut(&mut self) -> Option<&mut EventMetadata> {
+        None
+    }
+
+    ///
+    /// Shared access to the metadata of the event, e.g. to order it by a metadata entry.
+    ///
+    fn event_metadata(&self) -> Option<&EventMetadata> {
+        None
+    }
+
+    ///
+    /// Events with the same key are handled in order by the concurrent buses, e.g. the id of an aggregate.
+    ///
+    fn ordering_key(&self) -> Option<String> {
+        None
+    }
+}
+
+///
+/// The metadata key used to correlate events caused by the same operation.
+///
+pub const CORRELATION_ID: &str = "correlation-id";
+
+///
+/// The metadata key of the unique id of an event, e.g. the CloudEvents `id`.
+///
+pub const EVENT_ID: &str = "event-id";
+
+///
+/// Generates an id unique to the process, the default of the event ids and the correlation ids.
+///
+pub fn unique_id() -> String {
+    static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+
+    format!("{:x}-{:x}-{:x}", millis, std::process::id(), SEQUENCE.fetch_add(1, Ordering::Relaxed))
 }
 
 pub trait EventWithMetadata: AsAny + Sync + Send + 'static {
@@ -41,6 +89,10 @@ impl EventMetadata {
     pub fn get(&self, key: &str) -> Option<&String> {
         self.0.get(key)
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.0.iter()
+    }
 }
 
 #[macro_export]
@@ -71,6 +123,14 @@ macro_rules! event {
             fn event_version(&self) -> &'static str {
                 "1.0"
             }
+
+            fn metadata_mut(&mut self) -> Option<&mut hermes::event::EventMetadata> {
+                Some(&mut self.metadata)
+            }
+
+            fn event_metadata(&self) -> Option<&hermes::event::EventMetadata> {
+                Some(&self.metadata)
+            }
         }
 
         $crate::event_metadata!($event_name);
@@ -92,6 +152,14 @@ macro_rules! event {
             fn event_version(&self) -> &'static str {
                 "1.0"
             }
+
+            fn metadata_mut(&mut self) -> Option<&mut hermes::event::EventMetadata> {
+                Some(&mut self.metadata)
+            }
+
+            fn event_metadata(&self) -> Option<&hermes::event::EventMetadata> {
+                Some(&self.metadata)
+            }
         }
 
         $crate::event_metadata!($event_name);
@@ -101,7 +169,7 @@ macro_rules! event {
 #[macro_export]
 macro_rules! event_metadata {
     ($event_name:ident) => {
-        impl hermes::event::EventWithMetadata for $event_name {
+        impl $crate::event::EventWithMetadata for $event_name {
             fn add_metadata(&mut self, key: String, value: String) {
                 self.metadata.add(key, value);
             }
@@ -110,11 +178,11 @@ macro_rules! event_metadata {
                 self.metadata.get(key)
             }
 
-            fn metadata(&self) -> &hermes::event::EventMetadata {
+            fn metadata(&self) -> &$crate::event::EventMetadata {
                 &self.metadata
             }
 
-            fn drain_metadata(&mut self) -> hermes::event::EventMetadata {
+            fn drain_metadata(&mut self) -> $crate::event::EventMetadata {
                 std::mem::take(&mut self.metadata)
             }
         }
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::event_store::{EventStore, EventStoreError, ExpectedVersion, NewEvent, StoredEvent};
+
+#[derive(Default)]
+struct InMemoryEvents {
+    events: Vec<StoredEvent>,
+    streams: HashMap<String, Vec<usize>>,
+}
+
+///
+/// An event store that keeps every event in memory, useful for tests and prototypes.
+///
+#[derive(Default)]
+pub struct InMemoryEventStore {
+    inner: RwLock<InMemoryEvents>,
+}
+
+impl InMemoryEventStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl EventStore for InMemoryEventStore {
+    fn append(&self, stream_id: &str, expected_version: ExpectedVersion, events: Vec<NewEvent>) -> Result<u64, EventStoreError> {
+        let mut inner = self.inner.write().map_err(|e| EventStoreError::StorageError(e.to_string().into()))?;
+        let current_version = inner.streams.get(stream_id).map_or(0, |stream| stream.len() as u64);
+
+        if !expected_version.matches(current_version) {
+            return Err(EventStoreError::WrongExpectedVersion {
+                stream_id: stream_id.to_string(),
+                expected: expected_version,
+                actual: current_version,
+            });
+        }
+
+        let mut version = current_version;
+        for event in events {
+            version += 1;
+            let index = inner.events.len();
+
+            inner.events.push(StoredEvent {
+                stream_id: stream_id.to_string(),
+                version,
+                position: index as u64 + 1,
+                event_name: event.event_name,
+                payload: event.payload,
+            });
+
+            inner.streams
+                .entry(stream_id.to_string())
+                .or_default()
+                .push(index);
+        }
+
+        Ok(version)
+    }
+
+    fn read_stream(&self, stream_id: &str, from_version: u64) -> Result<Vec<StoredEvent>, EventStoreError> {
+        let inner = self.inner.read().map_err(|e| EventStoreError::StorageError(e.to_string().into()))?;
+
+        let events = match inner.streams.get(stream_id) {
+            Some(stream) => stream.iter()
+                                  .map(|index| &inner.events[*index])
+                                  .filter(|event| event.version >= from_version)
+                                  .cloned()
+                                  .collect(),
+            None => vec![],
+        };
+
+        Ok(events)
+    }
+
+    fn read_all(&self, from_position: u64, limit: usize) -> Result<Vec<StoredEvent>, EventStoreError> {
+        let inner = self.inner.read().map_err(|e| EventStoreError::StorageError(e.to_string().into()))?;
+        let start = from_position.saturating_sub(1) as usize;
+
+        Ok(
+            inner.events.iter()
+                 .skip(start)
+                 .take(limit)
+                 .cloned()
+                 .collect()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_event(event_name: &str) -> NewEvent {
+        NewEvent::new(event_name.to_string(), "{}".to_string())
+    }
+
+    #[test]
+    fn it_should_append_and_read_streams() {
+        let store = InMemoryEventStore::new();
+
+        store.append("account-1", ExpectedVersion::NoStream, vec![new_event("opened"), new_event("deposited")]).unwrap();
+        store.append("account-2", ExpectedVersion::NoStream, vec![new_event("opened")]).unwrap();
+        let version = store.append("account-1", ExpectedVersion::Exact(2), vec![new_event("withdrawn")]).unwrap();
+
+        assert_eq!(version, 3);
+
+        let stream = store.read_stream("account-1", 2).unwrap();
+        assert_eq!(stream.len(), 2);
+        assert_eq!(stream[0].event_name, "deposited");
+        assert_eq!(stream[1].position, 4);
+
+        let all = store.read_all(2, 2).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].stream_id, "account-1");
+        assert_eq!(all[1].stream_id, "account-2");
+    }
+
+    #[test]
+    fn it_should_reject_appends_with_wrong_expected_version() {
+        let store = InMemoryEventStore::new();
+
+        store.append("account-1", ExpectedVersion::NoStream, vec![new_event("opened")]).unwrap();
+        let result = store.append("account-1", ExpectedVersion::NoStream, vec![new_event("opened")]);
+
+        assert!(matches!(result, Err(EventStoreError::WrongExpectedVersion { actual: 1, .. })));
+    }
+}
@@ -0,0 +1,116 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+pub mod in_memory_event_store;
+pub mod snapshot_store;
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite_event_store;
+
+///
+/// An event ready to be appended to a stream, already serialized by any formatter.
+///
+#[derive(Debug, Clone)]
+pub struct NewEvent {
+    pub event_name: String,
+    pub payload: Vec<u8>,
+}
+
+impl NewEvent {
+    pub fn new(event_name: String, payload: impl Into<Vec<u8>>) -> Self {
+        Self {
+            event_name,
+            payload: payload.into()
+        }
+    }
+}
+
+///
+/// An event persisted in the store.
+///
+/// `version` is the 1-based position of the event inside its stream and `position`
+/// is the 1-based position of the event across all the streams of the store.
+///
+#[derive(Debug, Clone)]
+pub struct StoredEvent {
+    pub stream_id: String,
+    pub version: u64,
+    pub position: u64,
+    pub event_name: String,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectedVersion {
+    Any,
+    NoStream,
+    Exact(u64),
+}
+
+impl ExpectedVersion {
+    pub(crate) fn matches(&self, current_version: u64) -> bool {
+        match self {
+            ExpectedVersion::Any => true,
+            ExpectedVersion::NoStream => current_version == 0,
+            ExpectedVersion::Exact(version) => *version == current_version,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum EventStoreError {
+    WrongExpectedVersion { stream_id: String, expected: ExpectedVersion, actual: u64 },
+    StorageError(Box<dyn Error + Send + Sync>),
+    CannotSerializeEvent(Box<dyn Error + Send + Sync>),
+    CannotDeserializeEvent(Box<dyn Error + Send + Sync>),
+    CannotSerializeSnapshot(Box<dyn Error + Send + Sync>),
+    CannotDeserializeSnapshot(Box<dyn Error + Send + Sync>),
+    CannotPublishEvent(Box<dyn Error + Send + Sync>),
+}
+
+impl Display for EventStoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventStoreError::WrongExpectedVersion { stream_id, expected, actual } => {
+                write!(f, "Wrong expected version for stream {}: expected {:?}, actual {}", stream_id, expected, actual)
+            },
+            EventStoreError::StorageError(error) => write!(f, "StorageError: {}", error),
+            EventStoreError::CannotSerializeEvent(error) => write!(f, "Cannot serialize event: {}", error),
+            EventStoreError::CannotDeserializeEvent(error) => write!(f, "Cannot deserialize event: {}", error),
+            EventStoreError::CannotSerializeSnapshot(error) => write!(f, "Cannot serialize snapshot: {}", error),
+            EventStoreError::CannotDeserializeSnapshot(error) => write!(f, "Cannot deserialize snapshot: {}", error),
+            EventStoreError::CannotPublishEvent(error) => write!(f, "Cannot publish event: {}", error),
+        }
+    }
+}
+
+impl Error for EventStoreError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EventStoreError::CannotSerializeEvent(error)
+            | EventStoreError::CannotDeserializeEvent(error)
+            | EventStoreError::CannotSerializeSnapshot(error)
+            | EventStoreError::CannotDeserializeSnapshot(error)
+            | EventStoreError::CannotPublishEvent(error)
+            | EventStoreError::StorageError(error) => Some(error.as_ref()),
+            EventStoreError::WrongExpectedVersion { .. } => None,
+        }
+    }
+}
+
+pub trait EventStore: Send + Sync {
+    ///
+    /// Append events to a stream, returning the new version of the stream.
+    ///
+    fn append(&self, stream_id: &str, expected_version: ExpectedVersion, events: Vec<NewEvent>) -> Result<u64, EventStoreError>;
+
+    ///
+    /// Read the events of a stream starting at the given version (inclusive).
+    ///
+    fn read_stream(&self, stream_id: &str, from_version: u64) -> Result<Vec<StoredEvent>, EventStoreError>;
+
+    ///
+    /// Read at most `limit` events of all the streams starting at the given position (inclusive).
+    ///
+    fn read_all(&self, from_position: u64, limit: usize) -> Result<Vec<StoredEvent>, EventStoreError>;
+}
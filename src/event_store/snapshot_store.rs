@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::event_store::EventStoreError;
+
+///
+/// The serialized state of a stream at a given version.
+///
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub stream_id: String,
+    pub version: u64,
+    pub payload: Vec<u8>,
+}
+
+pub trait SnapshotStore: Send + Sync {
+    ///
+    /// Load the latest snapshot of a stream.
+    ///
+    fn load(&self, stream_id: &str) -> Result<Option<Snapshot>, EventStoreError>;
+
+    ///
+    /// Save a snapshot, replacing the previous one of the same stream.
+    ///
+    fn save(&self, snapshot: Snapshot) -> Result<(), EventStoreError>;
+}
+
+///
+/// Decides when a new snapshot should be taken after saving events.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SnapshotPolicy {
+    #[default]
+    Never,
+    EveryNEvents(u64),
+}
+
+impl SnapshotPolicy {
+    ///
+    /// Whether saving the events between both versions crossed a multiple of N.
+    ///
+    pub fn should_snapshot(&self, previous_version: u64, new_version: u64) -> bool {
+        match self {
+            SnapshotPolicy::Never => false,
+            SnapshotPolicy::EveryNEvents(0) => false,
+            SnapshotPolicy::EveryNEvents(events) => new_version / events > previous_version / events,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct InMemorySnapshotStore {
+    snapshots: RwLock<HashMap<String, Snapshot>>,
+}
+
+impl InMemorySnapshotStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SnapshotStore for InMemorySnapshotStore {
+    fn load(&self, stream_id: &str) -> Result<Option<Snapshot>, EventStoreError> {
+        let snapshots = self.snapshots.read().map_err(|e| EventStoreError::StorageError(e.to_string().into()))?;
+
+        Ok(snapshots.get(stream_id).cloned())
+    }
+
+    fn save(&self, snapshot: Snapshot) -> Result<(), EventStoreError> {
+        let mut snapshots = self.snapshots.write().map_err(|e| EventStoreError::StorageError(e.to_string().into()))?;
+        snapshots.insert(snapshot.stream_id.clone(), snapshot);
+
+        Ok(())
+    }
+}
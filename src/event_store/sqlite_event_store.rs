@@ -0,0 +1,205 @@
+use std::sync::Mutex;
+
+use rusqlite::{Connection, OptionalExtension, params};
+use rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE;
+
+use crate::event_store::{EventStore, EventStoreError, ExpectedVersion, NewEvent, StoredEvent};
+
+///
+/// An event store backed by a SQLite database.
+///
+pub struct SqliteEventStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteEventStore {
+    ///
+    /// Create a new SqliteEventStore, creating the `events` table if needed.
+    ///
+    pub fn new(connection: Connection) -> Result<Self, EventStoreError> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS events (
+                position INTEGER PRIMARY KEY AUTOINCREMENT,
+                stream_id TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                event_name TEXT NOT NULL,
+                payload BLOB NOT NULL,
+                UNIQUE (stream_id, version)
+            );"
+        ).map_err(storage_error)?;
+
+        Ok(Self { connection: Mutex::new(connection) })
+    }
+
+    ///
+    /// Create a new SqliteEventStore on a database file.
+    ///
+    pub fn open(path: &str) -> Result<Self, EventStoreError> {
+        let connection = Connection::open(path).map_err(storage_error)?;
+
+        Self::new(connection)
+    }
+
+    fn map_row(row: &rusqlite::Row) -> rusqlite::Result<StoredEvent> {
+        Ok(StoredEvent {
+            stream_id: row.get(0)?,
+            version: row.get::<_, i64>(1)? as u64,
+            position: row.get::<_, i64>(2)? as u64,
+            event_name: row.get(3)?,
+            // databases created before the payloads were binary keep them as text
+            payload: row.get_ref(4)?.as_bytes()?.to_vec(),
+        })
+    }
+}
+
+impl EventStore for SqliteEventStore {
+    fn append(&self, stream_id: &str, expected_version: ExpectedVersion, events: Vec<NewEvent>) -> Result<u64, EventStoreError> {
+        let mut connection = self.connection.lock().map_err(|e| EventStoreError::StorageError(e.to_string().into()))?;
+        let transaction = connection.transaction().map_err(storage_error)?;
+
+        let current_version = transaction
+            .query_row(
+                "SELECT MAX(version) FROM events WHERE stream_id = ?1",
+                params![stream_id],
+                |row| row.get::<_, Option<i64>>(0),
+            )
+            .optional()
+            .map_err(storage_error)?
+            .flatten()
+            .unwrap_or(0) as u64;
+
+        if !expected_version.matches(current_version) {
+            return Err(EventStoreError::WrongExpectedVersion {
+                stream_id: stream_id.to_string(),
+                expected: expected_version,
+                actual: current_version,
+            });
+        }
+
+        let mut version = current_version;
+        for event in events {
+            version += 1;
+
+            transaction.execute(
+                "INSERT INTO events (stream_id, version, event_name, payload) VALUES (?1, ?2, ?3, ?4)",
+                params![stream_id, version as i64, event.event_name, event.payload],
+            ).map_err(|e| match e {
+                // another connection appended to the stream since its version was read
+                rusqlite::Error::SqliteFailure(error, _) if error.extended_code == SQLITE_CONSTRAINT_UNIQUE => {
+                    EventStoreError::WrongExpectedVersion {
+                        stream_id: stream_id.to_string(),
+                        expected: expected_version,
+                        actual: version,
+                    }
+                },
+                e => storage_error(e),
+            })?;
+        }
+
+        transaction.commit().map_err(storage_error)?;
+
+        Ok(version)
+    }
+
+    fn read_stream(&self, stream_id: &str, from_version: u64) -> Result<Vec<StoredEvent>, EventStoreError> {
+        let connection = self.connection.lock().map_err(|e| EventStoreError::StorageError(e.to_string().into()))?;
+
+        let mut statement = connection.prepare(
+            "SELECT stream_id, version, position, event_name, payload FROM events
+            WHERE stream_id = ?1 AND version >= ?2 ORDER BY version"
+        ).map_err(storage_error)?;
+
+        let events = statement.query_map(params![stream_id, from_version as i64], Self::map_row)
+                              .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+                              .map_err(storage_error)?;
+
+        Ok(events)
+    }
+
+    fn read_all(&self, from_position: u64, limit: usize) -> Result<Vec<StoredEvent>, EventStoreError> {
+        let connection = self.connection.lock().map_err(|e| EventStoreError::StorageError(e.to_string().into()))?;
+
+        let mut statement = connection.prepare(
+            "SELECT stream_id, version, position, event_name, payload FROM events
+            WHERE position >= ?1 ORDER BY position LIMIT ?2"
+        ).map_err(storage_error)?;
+
+        let events = statement.query_map(params![from_position as i64, limit as i64], Self::map_row)
+                              .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+                              .map_err(storage_error)?;
+
+        Ok(events)
+    }
+}
+
+fn storage_error(error: rusqlite::Error) -> EventStoreError {
+    EventStoreError::StorageError(Box::new(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_append_and_read_events_from_sqlite() {
+        let store = SqliteEventStore::new(Connection::open_in_memory().unwrap()).unwrap();
+
+        store.append("account-1", ExpectedVersion::NoStream, vec![NewEvent::new("opened".to_string(), "{}".to_string())]).unwrap();
+        store.append("account-2", ExpectedVersion::Any, vec![NewEvent::new("opened".to_string(), "{}".to_string())]).unwrap();
+        let result = store.append("account-1", ExpectedVersion::Exact(0), vec![NewEvent::new("opened".to_string(), "{}".to_string())]);
+
+        assert!(matches!(result, Err(EventStoreError::WrongExpectedVersion { actual: 1, .. })));
+        assert_eq!(store.read_stream("account-1", 1).unwrap().len(), 1);
+        assert_eq!(store.read_all(2, 10).unwrap()[0].stream_id, "account-2");
+    }
+
+    #[test]
+    fn it_should_keep_binary_payloads_and_read_text_ones() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(
+            "CREATE TABLE events (
+                position INTEGER PRIMARY KEY AUTOINCREMENT,
+                stream_id TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                event_name TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                UNIQUE (stream_id, version)
+            );
+            INSERT INTO events (stream_id, version, event_name, payload) VALUES ('account-1', 1, 'opened', '{}');"
+        ).unwrap();
+        let store = SqliteEventStore::new(connection).unwrap();
+
+        store.append("account-1", ExpectedVersion::Exact(1), vec![NewEvent::new("deposited".to_string(), vec![0x81, 0xff, 0x00])]).unwrap();
+
+        let events = store.read_stream("account-1", 1).unwrap();
+        assert_eq!(events[0].payload, b"{}");
+        assert_eq!(events[1].payload, vec![0x81, 0xff, 0x00]);
+    }
+
+    #[test]
+    fn it_should_reject_an_append_racing_with_another_connection() {
+        let connection = Connection::open_in_memory().unwrap();
+        let store = SqliteEventStore::new(connection).unwrap();
+        store.connection.lock().unwrap().execute_batch(
+            "CREATE TRIGGER concurrent_append BEFORE INSERT ON events WHEN NEW.event_name = 'raced'
+            BEGIN
+                INSERT INTO events (stream_id, version, event_name, payload) VALUES (NEW.stream_id, NEW.version, 'appended', x'');
+            END;"
+        ).unwrap();
+
+        let result = store.append("account-1", ExpectedVersion::NoStream, vec![NewEvent::new("raced".to_string(), "{}".to_string())]);
+
+        assert!(matches!(result, Err(EventStoreError::WrongExpectedVersion { actual: 1, .. })));
+        assert!(store.read_stream("account-1", 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_should_keep_the_sqlite_error_as_the_source_of_a_storage_error() {
+        let Err(error) = SqliteEventStore::open("/nonexistent/events.db") else {
+            panic!("The database should not be opened");
+        };
+
+        assert!(matches!(error, EventStoreError::StorageError(_)));
+        assert!(std::error::Error::source(&error).unwrap().downcast_ref::<rusqlite::Error>().is_some());
+    }
+}
@@ -3,17 +3,33 @@
 pub mod event;
 pub mod subscriber;
 pub mod bus;
+pub mod command;
+pub mod query;
 
 #[cfg(feature = "serializer")]
 pub mod serializer;
 
-#[cfg(feature = "rabbit")]
+#[cfg(feature = "serializer")]
+pub mod event_store;
+
+#[cfg(feature = "serializer")]
+pub mod aggregate;
+
+#[cfg(feature = "serializer")]
+pub mod projection;
+
 #[cfg(feature = "async")]
 pub mod consumer;
 
+#[cfg(feature = "async")]
+pub mod saga;
+
 #[cfg(feature = "rabbit")]
 pub mod rabbit;
 
+#[cfg(feature = "postgres")]
+pub mod postgres;
+
 #[cfg(feature = "derive")]
 pub mod derive {
     pub use hermes_derive::Event;
@@ -0,0 +1,33 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+pub mod postgres_listener;
+pub mod postgres_publisher;
+pub mod postgres_configurer;
+
+#[derive(Debug)]
+pub enum PostgresError {
+    CannotListen(tokio_postgres::Error),
+    CannotLeaseEvents(tokio_postgres::Error),
+    CannotUpdateEvent(tokio_postgres::Error),
+}
+
+impl Display for PostgresError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostgresError::CannotListen(error) => write!(f, "Cannot listen for notifications: {}", error),
+            PostgresError::CannotLeaseEvents(error) => write!(f, "Cannot lease events: {}", error),
+            PostgresError::CannotUpdateEvent(error) => write!(f, "Cannot update event: {}", error),
+        }
+    }
+}
+
+impl Error for PostgresError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PostgresError::CannotListen(error)
+            | PostgresError::CannotLeaseEvents(error)
+            | PostgresError::CannotUpdateEvent(error) => Some(error),
+        }
+    }
+}
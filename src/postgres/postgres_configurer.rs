@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use tokio_postgres::Client;
+
+pub struct PostgresConfigurer {
+    client: Arc<Client>,
+    table: String,
+}
+
+impl PostgresConfigurer {
+    pub fn new(
+        client: Arc<Client>,
+        table: String,
+    ) -> Self {
+        PostgresConfigurer { client, table }
+    }
+
+    pub async fn configure(&self, queue: (&str, &[&str])) {
+        self.create_tables().await;
+        self.bind_queue(queue.0, queue.1).await;
+    }
+
+    async fn create_tables(&self) {
+        self.client.batch_execute(create_tables_statement(&self.table).as_str()).await.expect("Cannot create event tables");
+    }
+
+    async fn bind_queue(&self, queue_name: &str, event_names: &[&str]) {
+        let statement = bind_queue_statement(&self.table);
+
+        for event_name in event_names {
+            self.client
+                .execute(statement.as_str(), &[&queue_name, event_name])
+                .await
+                .expect("Cannot bind queue to event");
+        }
+    }
+}
+
+fn create_tables_statement(table: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+            id BIGSERIAL PRIMARY KEY,
+            queue TEXT NOT NULL,
+            event_name TEXT NOT NULL,
+            payload BYTEA NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            attempts INTEGER NOT NULL DEFAULT 0,
+            requeues INTEGER NOT NULL DEFAULT 0,
+            available_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            locked_until TIMESTAMPTZ,
+            last_error TEXT,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+        ALTER TABLE {table} ADD COLUMN IF NOT EXISTS requeues INTEGER NOT NULL DEFAULT 0;
+        CREATE INDEX IF NOT EXISTS {table}_queue_status_idx ON {table} (queue, status, available_at);
+        CREATE TABLE IF NOT EXISTS {table}_bindings (
+            queue TEXT NOT NULL,
+            event_name TEXT NOT NULL,
+            PRIMARY KEY (queue, event_name)
+        );"
+    )
+}
+
+fn bind_queue_statement(table: &str) -> String {
+    format!("INSERT INTO {}_bindings (queue, event_name) VALUES ($1, $2) ON CONFLICT DO NOTHING", table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_create_the_event_and_binding_tables_of_the_table() {
+        let statement = create_tables_statement("events");
+
+        assert!(statement.contains("CREATE TABLE IF NOT EXISTS events ("));
+        assert!(statement.contains("status TEXT NOT NULL DEFAULT 'pending'"));
+        assert!(statement.contains("ADD COLUMN IF NOT EXISTS requeues INTEGER NOT NULL DEFAULT 0"));
+        assert!(statement.contains("CREATE INDEX IF NOT EXISTS events_queue_status_idx ON events (queue, status, available_at)"));
+        assert!(statement.contains("CREATE TABLE IF NOT EXISTS events_bindings ("));
+    }
+
+    #[test]
+    fn it_should_bind_queues_idempotently() {
+        assert_eq!(
+            bind_queue_statement("events"),
+            "INSERT INTO events_bindings (queue, event_name) VALUES ($1, $2) ON CONFLICT DO NOTHING"
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use futures_lite::stream::{self, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio_postgres::{AsyncMessage, Connection, Notification};
+
+///
+/// Drives a tokio-postgres connection and forwards the notifications it receives.
+///
+/// tokio-postgres only delivers `NOTIFY` messages to whoever polls the connection,
+/// so the connection must be handed to the listener instead of being spawned directly.
+///
+pub struct PostgresListener {
+    notifications: UnboundedReceiver<Notification>,
+}
+
+impl PostgresListener {
+    pub fn spawn<S, T>(mut connection: Connection<S, T>) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static
+    {
+        let (sender, notifications) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+
+            while let Some(message) = messages.next().await {
+                match message {
+                    Ok(AsyncMessage::Notification(notification)) => {
+                        let _ = sender.send(notification);
+                    },
+                    Ok(_) => {},
+                    Err(e) => {
+                        log::error!("Postgres connection error: {:?}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self { notifications }
+    }
+
+    pub async fn recv(&mut self) -> Option<Notification> {
+        self.notifications.recv().await
+    }
+}
@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use tokio_postgres::Client;
+
+use crate::bus::error::PublishError;
+
+pub struct PostgresPublisher {
+    client: Arc<Client>,
+}
+
+impl PostgresPublisher {
+    pub fn new(client: Arc<Client>) -> Self {
+        PostgresPublisher { client }
+    }
+
+    ///
+    /// Stores the payload once for every queue bound to the event name and notifies
+    /// the consumers of those queues. The table name is trusted and not escaped.
+    ///
+    pub async fn publish(&self, payload: &[u8], event_name: &str, table: &str) -> Result<(), PublishError> {
+        let statement = format!(
+            "WITH inserted AS (
+                INSERT INTO {table} (queue, event_name, payload)
+                SELECT queue, $1, $2 FROM {table}_bindings WHERE event_name = $1
+                RETURNING queue
+            )
+            SELECT pg_notify('{table}', queue) FROM inserted GROUP BY queue"
+        );
+
+        self.client
+            .execute(statement.as_str(), &[&event_name, &payload])
+            .await
+            .map_err(|e| PublishError::CannotPublishEvent(Box::new(e)))?;
+
+        Ok(())
+    }
+}
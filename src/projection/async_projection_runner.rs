@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::event::{Event, EventName};
+use crate::event_store::StoredEvent;
+use crate::projection::{CheckpointStore, EventSource, Projection, ProjectionError};
+use crate::serializer::EventDeserializer;
+use crate::subscriber::{AsyncSubscriber, SubscriberError};
+
+const BATCH_SIZE: usize = 100;
+
+type HandlerFuture = Pin<Box<dyn Future<Output = Result<(), SubscriberError>> + Send>>;
+
+type ProjectionClosure<'a> = Box<dyn Fn(&StoredEvent) -> Result<HandlerFuture, ProjectionError> + Send + Sync + 'a>;
+
+///
+/// Replays the events of a source through the `AsyncSubscriber` implementations of a projection,
+/// saving a checkpoint after every processed event.
+///
+pub struct AsyncProjectionRunner<'a, P, S, C, D>
+where
+    P: Projection + Send + Sync + 'static,
+    S: EventSource,
+    C: CheckpointStore,
+    D: EventDeserializer
+{
+    projection: Arc<P>,
+    source: Arc<S>,
+    checkpoints: Arc<C>,
+    deserializer: &'a D,
+    handlers: HashMap<&'static str, Vec<ProjectionClosure<'a>>>,
+}
+
+impl<'a, P, S, C, D> AsyncProjectionRunner<'a, P, S, C, D>
+where
+    P: Projection + Send + Sync + 'static,
+    S: EventSource,
+    C: CheckpointStore,
+    D: EventDeserializer
+{
+    pub fn new(projection: Arc<P>, source: Arc<S>, checkpoints: Arc<C>, deserializer: &'a D) -> Self {
+        Self {
+            projection,
+            source,
+            checkpoints,
+            deserializer,
+            handlers: HashMap::new(),
+        }
+    }
+
+    ///
+    /// Deliver the events named `E::static_event_name()` to the projection.
+    ///
+    pub fn register<E>(&mut self)
+    where
+        E: Event + EventName + Serialize + DeserializeOwned,
+        P: AsyncSubscriber<E>
+    {
+        let projection = self.projection.clone();
+        let deserializer = self.deserializer;
+
+        let handler: ProjectionClosure<'a> = Box::new(move |stored_event| {
+            let event = deserializer.deserialize::<E>(&stored_event.payload)
+                                    .map_err(|_| ProjectionError::CannotDeserializeEvent { position: stored_event.position })?
+                                    .data
+                                    .attributes;
+            let projection = projection.clone();
+
+            Ok(Box::pin(async move {
+                projection.handle_event(&event).await
+            }))
+        });
+
+        self.handlers
+            .entry(E::static_event_name())
+            .or_default()
+            .push(handler);
+    }
+
+    ///
+    /// Process every event after the checkpoint, returning how many events were processed.
+    ///
+    pub async fn catch_up(&self) -> Result<u64, ProjectionError> {
+        let projection_name = self.projection.projection_name();
+        let mut checkpoint = self.checkpoints.load(projection_name)?;
+        let mut processed = 0;
+
+        loop {
+            let events = self.source.read_from(checkpoint + 1, BATCH_SIZE)?;
+            if events.is_empty() {
+                return Ok(processed);
+            }
+
+            for event in events {
+                self.dispatch(&event).await?;
+
+                checkpoint = event.position;
+                self.checkpoints.save(projection_name, checkpoint)?;
+                processed += 1;
+            }
+        }
+    }
+
+    ///
+    /// Reset the projection and replay every event from the start of the source.
+    ///
+    pub async fn rebuild(&self) -> Result<u64, ProjectionError> {
+        self.projection.reset()?;
+        self.checkpoints.save(self.projection.projection_name(), 0)?;
+
+        self.catch_up().await
+    }
+
+    ///
+    /// Catch up and keep polling the source for new events until `running` is false.
+    ///
+    pub async fn run_live(&self, poll_interval: Duration, running: &AtomicBool) -> Result<(), ProjectionError> {
+        while running.load(Ordering::Relaxed) {
+            self.catch_up().await?;
+            tokio::time::sleep(poll_interval).await;
+        }
+
+        Ok(())
+    }
+
+    async fn dispatch(&self, event: &StoredEvent) -> Result<(), ProjectionError> {
+        if let Some(handlers) = self.handlers.get(event.event_name.as_str()) {
+            for handler in handlers {
+                handler(event)?
+                    .await
+                    .map_err(ProjectionError::SubscriberError)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU64;
+
+    use crate::event_store::{EventStore, ExpectedVersion, NewEvent};
+    use crate::event_store::in_memory_event_store::InMemoryEventStore;
+    use crate::projection::InMemoryCheckpointStore;
+    use crate::serializer::EventSerializer;
+    use crate::serializer::fixtures::SerializableEvent;
+    use crate::serializer::serde_formatter::SerdeJSONEventFormatter;
+
+    use super::*;
+
+    impl EventName for SerializableEvent {
+        fn static_event_name() -> &'static str {
+            "serializable_event"
+        }
+    }
+
+    #[derive(Default)]
+    struct EventCount {
+        count: AtomicU64
+    }
+
+    impl Projection for EventCount {
+        fn projection_name(&self) -> &'static str {
+            "event_count"
+        }
+
+        fn reset(&self) -> Result<(), ProjectionError> {
+            self.count.store(0, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    impl AsyncSubscriber<SerializableEvent> for EventCount {
+        async fn handle_event(&self, _event: &SerializableEvent) -> Result<(), SubscriberError> {
+            self.count.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    fn append(store: &InMemoryEventStore, id: &str) {
+        let event = SerializableEvent::new(id);
+        let payload = SerdeJSONEventFormatter.serialize(&event).unwrap();
+
+        store.append("stream-1", ExpectedVersion::Any, vec![
+            NewEvent::new(event.event_name().to_string(), payload),
+            NewEvent::new("unregistered_event".to_string(), "{}".to_string()),
+        ]).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_should_catch_up_from_the_checkpoint_and_rebuild() {
+        let store = Arc::new(InMemoryEventStore::new());
+        let checkpoints = Arc::new(InMemoryCheckpointStore::default());
+        let projection = Arc::new(EventCount::default());
+
+        let mut runner = AsyncProjectionRunner::new(projection.clone(), store.clone(), checkpoints.clone(), &SerdeJSONEventFormatter);
+        runner.register::<SerializableEvent>();
+
+        append(&store, "1");
+        assert_eq!(runner.catch_up().await.unwrap(), 2);
+
+        append(&store, "2");
+        assert_eq!(runner.catch_up().await.unwrap(), 2);
+        assert_eq!(projection.count.load(Ordering::Relaxed), 2);
+        assert_eq!(checkpoints.load("event_count").unwrap(), 4);
+
+        assert_eq!(runner.rebuild().await.unwrap(), 4);
+        assert_eq!(projection.count.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn it_should_stop_running_live_when_signalled() {
+        let store = Arc::new(InMemoryEventStore::new());
+        let checkpoints = Arc::new(InMemoryCheckpointStore::default());
+        let projection = Arc::new(EventCount::default());
+        let running = AtomicBool::new(true);
+
+        let mut runner = AsyncProjectionRunner::new(projection.clone(), store.clone(), checkpoints, &SerdeJSONEventFormatter);
+        runner.register::<SerializableEvent>();
+        append(&store, "1");
+
+        let stop = async {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            running.store(false, Ordering::Relaxed);
+        };
+
+        let (result, _) = tokio::time::timeout(
+            Duration::from_secs(1),
+            async { tokio::join!(runner.run_live(Duration::from_millis(5), &running), stop) }
+        ).await.unwrap();
+
+        assert!(result.is_ok());
+        assert_eq!(projection.count.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,147 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde_json::Value;
+
+use crate::event_store::StoredEvent;
+use crate::projection::{EventSource, ProjectionError};
+use crate::serializer::EventDeserializer;
+
+///
+/// Reads serialized events from a file with one event per line.
+///
+/// The position of an event is its 1-based line number, and the whole file is
+/// read as a single stream named after the file. A last line without its newline
+/// is left until it is complete. The byte offset of every line read so far is kept,
+/// so a read seeks to its position instead of scanning the file.
+///
+pub struct LogFileEventSource<'a, D: EventDeserializer> {
+    path: PathBuf,
+    deserializer: &'a D,
+    offsets: Mutex<Vec<u64>>,
+}
+
+impl<'a, D: EventDeserializer> LogFileEventSource<'a, D> {
+    pub fn new(path: PathBuf, deserializer: &'a D) -> Self {
+        Self {
+            path,
+            deserializer,
+            offsets: Mutex::new(vec![0]),
+        }
+    }
+}
+
+impl<D: EventDeserializer> EventSource for LogFileEventSource<'_, D> {
+    fn read_from(&self, from_position: u64, limit: usize) -> Result<Vec<StoredEvent>, ProjectionError> {
+        let mut file = File::open(&self.path).map_err(|e| ProjectionError::SourceError(e.to_string()))?;
+        let mut offsets = self.offsets.lock().map_err(|e| ProjectionError::SourceError(e.to_string()))?;
+        let stream_id = self.path.to_string_lossy().to_string();
+        let mut events = vec![];
+
+        // offsets[i] is where the line at position i + 1 starts
+        let mut position = from_position.clamp(1, offsets.len() as u64);
+        let mut offset = offsets[position as usize - 1];
+        file.seek(SeekFrom::Start(offset)).map_err(|e| ProjectionError::SourceError(e.to_string()))?;
+
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+
+        while events.len() < limit {
+            line.clear();
+            let read = reader.read_line(&mut line).map_err(|e| ProjectionError::SourceError(e.to_string()))?;
+            // a line without its newline may still be being written, so it is read once complete
+            if read == 0 || !line.ends_with('\n') {
+                break;
+            }
+
+            offset += read as u64;
+            if position as usize == offsets.len() {
+                offsets.push(offset);
+            }
+
+            let payload = line.trim_end_matches(['\n', '\r']);
+            if position >= from_position && !payload.trim().is_empty() {
+                let event_name = self.deserializer
+                                     .deserialize::<Value>(payload.as_bytes())
+                                     .map_err(|_| ProjectionError::CannotDeserializeEvent { position })?
+                                     .data
+                                     .event_name;
+
+                events.push(StoredEvent {
+                    stream_id: stream_id.clone(),
+                    version: position,
+                    position,
+                    event_name,
+                    payload: payload.as_bytes().to_vec(),
+                });
+            }
+
+            position += 1;
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+
+    use crate::serializer::EventSerializer;
+    use crate::serializer::fixtures::SerializableEvent;
+    use crate::serializer::serde_formatter::SerdeJSONEventFormatter;
+
+    use super::*;
+
+    fn line(id: &str) -> String {
+        let payload = SerdeJSONEventFormatter.serialize(&SerializableEvent::new(id)).unwrap();
+
+        String::from_utf8(payload).unwrap() + "\n"
+    }
+
+    #[test]
+    fn it_should_read_the_events_from_a_position_and_the_lines_appended_later() {
+        let path = std::env::temp_dir().join(format!("hermes-log-file-event-source-{}.log", std::process::id()));
+        fs::write(&path, line("1") + "\n" + &line("2") + &line("3")).unwrap();
+        let source = LogFileEventSource::new(path.clone(), &SerdeJSONEventFormatter);
+
+        let events = source.read_from(1, 2).unwrap();
+        assert_eq!(events.iter().map(|e| e.position).collect::<Vec<_>>(), vec![1, 3]);
+
+        let events = source.read_from(4, 10).unwrap();
+        assert_eq!(events.iter().map(|e| e.position).collect::<Vec<_>>(), vec![4]);
+
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(line("4").as_bytes()).unwrap();
+
+        let events = source.read_from(5, 10).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].position, 5);
+        assert_eq!(events[0].payload, line("4").trim_end().as_bytes());
+        assert_eq!(source.offsets.lock().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn it_should_leave_a_partially_written_last_line_until_it_is_complete() {
+        let path = std::env::temp_dir().join(format!("hermes-log-file-partial-line-{}.log", std::process::id()));
+        let complete = line("2");
+        let (written, rest) = complete.split_at(10);
+        fs::write(&path, line("1") + written).unwrap();
+        let source = LogFileEventSource::new(path.clone(), &SerdeJSONEventFormatter);
+
+        let events = source.read_from(1, 10).unwrap();
+        assert_eq!(events.iter().map(|e| e.position).collect::<Vec<_>>(), vec![1]);
+
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(rest.as_bytes()).unwrap();
+
+        let events = source.read_from(2, 10).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].payload, complete.trim_end().as_bytes());
+    }
+}
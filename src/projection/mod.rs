@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::sync::RwLock;
+
+use crate::event_store::{EventStore, StoredEvent};
+use crate::subscriber::SubscriberError;
+
+pub mod log_file_event_source;
+pub mod projection_runner;
+
+#[cfg(feature = "async")]
+pub mod async_projection_runner;
+
+///
+/// A read model built from an event stream.
+///
+/// The events are delivered through the `Subscriber`/`AsyncSubscriber` implementations
+/// of the projection, registered in a projection runner.
+///
+pub trait Projection {
+    fn projection_name(&self) -> &'static str;
+
+    ///
+    /// Clear the read model before it is rebuilt from the first event.
+    ///
+    fn reset(&self) -> Result<(), ProjectionError>;
+}
+
+///
+/// A source of events ordered by their global position.
+///
+pub trait EventSource: Send + Sync {
+    fn read_from(&self, from_position: u64, limit: usize) -> Result<Vec<StoredEvent>, ProjectionError>;
+}
+
+impl<S: EventStore> EventSource for S {
+    fn read_from(&self, from_position: u64, limit: usize) -> Result<Vec<StoredEvent>, ProjectionError> {
+        self.read_all(from_position, limit)
+            .map_err(|e| ProjectionError::SourceError(e.to_string()))
+    }
+}
+
+///
+/// Stores the position of the last event processed by each projection.
+///
+pub trait CheckpointStore: Send + Sync {
+    fn load(&self, projection_name: &str) -> Result<u64, ProjectionError>;
+
+    fn save(&self, projection_name: &str, position: u64) -> Result<(), ProjectionError>;
+}
+
+#[derive(Default)]
+pub struct InMemoryCheckpointStore {
+    checkpoints: RwLock<HashMap<String, u64>>,
+}
+
+impl CheckpointStore for InMemoryCheckpointStore {
+    fn load(&self, projection_name: &str) -> Result<u64, ProjectionError> {
+        let checkpoints = self.checkpoints.read().map_err(|e| ProjectionError::CheckpointError(e.to_string()))?;
+
+        Ok(checkpoints.get(projection_name).copied().unwrap_or(0))
+    }
+
+    fn save(&self, projection_name: &str, position: u64) -> Result<(), ProjectionError> {
+        let mut checkpoints = self.checkpoints.write().map_err(|e| ProjectionError::CheckpointError(e.to_string()))?;
+        checkpoints.insert(projection_name.to_string(), position);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum ProjectionError {
+    SourceError(String),
+    CheckpointError(String),
+    ResetError(String),
+    CannotDeserializeEvent { position: u64 },
+    SubscriberError(SubscriberError),
+}
+
+impl Display for ProjectionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProjectionError::SourceError(error) => write!(f, "SourceError: {}", error),
+            ProjectionError::CheckpointError(error) => write!(f, "CheckpointError: {}", error),
+            ProjectionError::ResetError(error) => write!(f, "ResetError: {}", error),
+            ProjectionError::CannotDeserializeEvent { position } => write!(f, "Cannot deserialize event at position {}", position),
+            ProjectionError::SubscriberError(error) => write!(f, "SubscriberError: {}", error),
+        }
+    }
+}
+
+impl Error for ProjectionError {}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::event::{Event, EventName};
+use crate::event_store::StoredEvent;
+use crate::projection::{CheckpointStore, EventSource, Projection, ProjectionError};
+use crate::serializer::EventDeserializer;
+use crate::subscriber::Subscriber;
+
+const BATCH_SIZE: usize = 100;
+
+type ProjectionClosure<'a> = Box<dyn Fn(&StoredEvent) -> Result<(), ProjectionError> + 'a>;
+
+///
+/// Replays the events of a source through the `Subscriber` implementations of a projection,
+/// saving a checkpoint after every processed event.
+///
+pub struct ProjectionRunner<'a, P, S, C, D>
+where
+    P: Projection + 'static,
+    S: EventSource,
+    C: CheckpointStore,
+    D: EventDeserializer
+{
+    projection: Rc<P>,
+    source: Arc<S>,
+    checkpoints: Arc<C>,
+    deserializer: &'a D,
+    handlers: HashMap<&'static str, Vec<ProjectionClosure<'a>>>,
+}
+
+impl<'a, P, S, C, D> ProjectionRunner<'a, P, S, C, D>
+where
+    P: Projection + 'static,
+    S: EventSource,
+    C: CheckpointStore,
+    D: EventDeserializer
+{
+    pub fn new(projection: Rc<P>, source: Arc<S>, checkpoints: Arc<C>, deserializer: &'a D) -> Self {
+        Self {
+            projection,
+            source,
+            checkpoints,
+            deserializer,
+            handlers: HashMap::new(),
+        }
+    }
+
+    ///
+    /// Deliver the events named `E::static_event_name()` to the projection.
+    ///
+    pub fn register<E>(&mut self)
+    where
+        E: Event + EventName + Serialize + DeserializeOwned,
+        P: Subscriber<E>
+    {
+        let projection = self.projection.clone();
+        let deserializer = self.deserializer;
+
+        let handler: ProjectionClosure<'a> = Box::new(move |stored_event| {
+            let event = deserializer.deserialize::<E>(&stored_event.payload)
+                                    .map_err(|_| ProjectionError::CannotDeserializeEvent { position: stored_event.position })?
+                                    .data
+                                    .attributes;
+
+            projection.handle_event(&event).map_err(ProjectionError::SubscriberError)
+        });
+
+        self.handlers
+            .entry(E::static_event_name())
+            .or_default()
+            .push(handler);
+    }
+
+    ///
+    /// Process every event after the checkpoint, returning how many events were processed.
+    ///
+    pub fn catch_up(&self) -> Result<u64, ProjectionError> {
+        let projection_name = self.projection.projection_name();
+        let mut checkpoint = self.checkpoints.load(projection_name)?;
+        let mut processed = 0;
+
+        loop {
+            let events = self.source.read_from(checkpoint + 1, BATCH_SIZE)?;
+            if events.is_empty() {
+                return Ok(processed);
+            }
+
+            for event in events {
+                self.dispatch(&event)?;
+
+                checkpoint = event.position;
+                self.checkpoints.save(projection_name, checkpoint)?;
+                processed += 1;
+            }
+        }
+    }
+
+    ///
+    /// Reset the projection and replay every event from the start of the source.
+    ///
+    pub fn rebuild(&self) -> Result<u64, ProjectionError> {
+        self.projection.reset()?;
+        self.checkpoints.save(self.projection.projection_name(), 0)?;
+
+        self.catch_up()
+    }
+
+    ///
+    /// Catch up and keep polling the source for new events until `running` is false.
+    ///
+    pub fn run_live(&self, poll_interval: Duration, running: &AtomicBool) -> Result<(), ProjectionError> {
+        while running.load(Ordering::Relaxed) {
+            self.catch_up()?;
+            thread::sleep(poll_interval);
+        }
+
+        Ok(())
+    }
+
+    fn dispatch(&self, event: &StoredEvent) -> Result<(), ProjectionError> {
+        if let Some(handlers) = self.handlers.get(event.event_name.as_str()) {
+            for handler in handlers {
+                handler(event)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use serde::Deserialize;
+
+    use crate::event::EventMetadata;
+    use crate::event_store::{EventStore, ExpectedVersion, NewEvent};
+    use crate::event_store::in_memory_event_store::InMemoryEventStore;
+    use crate::projection::InMemoryCheckpointStore;
+    use crate::serializer::EventSerializer;
+    use crate::serializer::serde_formatter::SerdeJSONEventFormatter;
+    use crate::subscriber::SubscriberError;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct MoneyDeposited {
+        amount: u64,
+        metadata: EventMetadata
+    }
+
+    impl Event for MoneyDeposited {
+        fn event_name(&self) -> &'static str {
+            Self::static_event_name()
+        }
+    }
+
+    impl EventName for MoneyDeposited {
+        fn static_event_name() -> &'static str {
+            "money_deposited"
+        }
+    }
+
+    crate::event_metadata!(MoneyDeposited);
+
+    #[derive(Default)]
+    struct TotalDeposited {
+        total: RefCell<u64>
+    }
+
+    impl Projection for TotalDeposited {
+        fn projection_name(&self) -> &'static str {
+            "total_deposited"
+        }
+
+        fn reset(&self) -> Result<(), ProjectionError> {
+            *self.total.borrow_mut() = 0;
+            Ok(())
+        }
+    }
+
+    impl Subscriber<MoneyDeposited> for TotalDeposited {
+        fn handle_event(&self, event: &MoneyDeposited) -> Result<(), SubscriberError> {
+            *self.total.borrow_mut() += event.amount;
+            Ok(())
+        }
+    }
+
+    fn deposit(store: &InMemoryEventStore, amount: u64) {
+        let event = MoneyDeposited { amount, metadata: EventMetadata::default() };
+        let payload = SerdeJSONEventFormatter.serialize(&event).unwrap();
+
+        store.append("account-1", ExpectedVersion::Any, vec![
+            NewEvent::new(event.event_name().to_string(), payload),
+            NewEvent::new("account_renamed".to_string(), "{}".to_string()),
+        ]).unwrap();
+    }
+
+    #[test]
+    fn it_should_catch_up_from_the_checkpoint_and_rebuild() {
+        let store = Arc::new(InMemoryEventStore::new());
+        let checkpoints = Arc::new(InMemoryCheckpointStore::default());
+        let projection = Rc::new(TotalDeposited::default());
+
+        let mut runner = ProjectionRunner::new(projection.clone(), store.clone(), checkpoints.clone(), &SerdeJSONEventFormatter);
+        runner.register::<MoneyDeposited>();
+
+        deposit(&store, 10);
+        assert_eq!(runner.catch_up().unwrap(), 2);
+
+        deposit(&store, 5);
+        assert_eq!(runner.catch_up().unwrap(), 2);
+        assert_eq!(*projection.total.borrow(), 15);
+        assert_eq!(checkpoints.load("total_deposited").unwrap(), 4);
+
+        assert_eq!(runner.rebuild().unwrap(), 4);
+        assert_eq!(*projection.total.borrow(), 15);
+    }
+}
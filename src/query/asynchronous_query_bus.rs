@@ -0,0 +1,138 @@
+use std::any::{Any, type_name, TypeId};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::query::{AsyncQueryHandler, AsyncQueryMiddleware, AsynchronousQueryBus, Query, QueryBusError, QueryNext};
+
+///
+/// An asynchronous query bus that answers every query in a tokio task.
+///
+#[derive(Default)]
+pub struct TokioQueryBus {
+    handlers: HashMap<TypeId, Box<dyn Any + Send + Sync>>
+}
+
+impl TokioQueryBus {
+    pub fn register<Q, H>(&mut self, handler: Arc<H>) -> Result<(), QueryBusError>
+    where
+        Q: Query,
+        H: AsyncQueryHandler<Q>
+    {
+        match self.handlers.entry(TypeId::of::<Q>()) {
+            Entry::Occupied(_) => Err(QueryBusError::HandlerAlreadyRegistered(type_name::<Q>())),
+            Entry::Vacant(entry) => {
+                let closure: QueryNext<Q> = Arc::new(move |query| {
+                    let handler = handler.clone();
+
+                    Box::pin(async move {
+                        handler.handle(&query).await
+                    })
+                });
+                entry.insert(Box::new(closure));
+
+                Ok(())
+            }
+        }
+    }
+
+    ///
+    /// Wrap the handler of a query type with a middleware.
+    ///
+    /// The handler must be registered first, and the last middleware added runs first.
+    ///
+    pub fn add_middleware<Q, M>(&mut self, middleware: Arc<M>) -> Result<(), QueryBusError>
+    where
+        Q: Query,
+        M: AsyncQueryMiddleware<Q>
+    {
+        let next = self.handler::<Q>()?.clone();
+        let closure: QueryNext<Q> = Arc::new(move |query| {
+            let middleware = middleware.clone();
+            let next = next.clone();
+
+            Box::pin(async move {
+                middleware.handle(query, next).await
+            })
+        });
+
+        self.handlers.insert(TypeId::of::<Q>(), Box::new(closure));
+
+        Ok(())
+    }
+
+    fn handler<Q: Query>(&self) -> Result<&QueryNext<Q>, QueryBusError> {
+        self.handlers
+            .get(&TypeId::of::<Q>())
+            .and_then(|handler| handler.downcast_ref::<QueryNext<Q>>())
+            .ok_or(QueryBusError::HandlerNotFound(type_name::<Q>()))
+    }
+}
+
+impl AsynchronousQueryBus for TokioQueryBus {
+    async fn ask<Q: Query>(&self, query: Q) -> Result<Q::Response, QueryBusError> {
+        let handler = self.handler::<Q>()?;
+
+        tokio::spawn(handler(Arc::new(query)))
+            .await
+            .map_err(|e| {
+                log::error!("Error while processing query: {:?}", e);
+                QueryBusError::HandlerPanicked(type_name::<Q>())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use crate::query::middleware::{CachingMiddleware, TimingMiddleware};
+
+    use super::*;
+
+    #[derive(Clone, Hash, PartialEq, Eq)]
+    struct CountOrders {
+        customer: String
+    }
+
+    impl Query for CountOrders {
+        type Response = u32;
+    }
+
+    #[derive(Default)]
+    struct OrderCounter {
+        calls: AtomicU32
+    }
+
+    impl AsyncQueryHandler<CountOrders> for OrderCounter {
+        async fn handle(&self, query: &CountOrders) -> u32 {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+
+            if query.customer.is_empty() {
+                panic!("Unknown customer");
+            }
+
+            query.customer.len() as u32
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_answer_queries_through_the_middlewares() {
+        let mut query_bus = TokioQueryBus::default();
+        let handler = Arc::new(OrderCounter::default());
+
+        query_bus.register(handler.clone()).unwrap();
+        query_bus.add_middleware::<CountOrders, _>(Arc::new(CachingMiddleware::new())).unwrap();
+        query_bus.add_middleware::<CountOrders, _>(Arc::new(TimingMiddleware)).unwrap();
+
+        let query = CountOrders { customer: "customer".to_string() };
+
+        assert_eq!(query_bus.ask(query.clone()).await.unwrap(), 8);
+        assert_eq!(query_bus.ask(query).await.unwrap(), 8);
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 1);
+        assert!(matches!(
+            query_bus.ask(CountOrders { customer: String::new() }).await,
+            Err(QueryBusError::HandlerPanicked(_))
+        ));
+    }
+}
@@ -0,0 +1,228 @@
+use std::any::type_name;
+use std::collections::HashMap;
+use std::hash::Hash;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "async")]
+use crate::query::{AsyncQueryMiddleware, QueryNext};
+use crate::query::{Query, QueryMiddleware};
+
+///
+/// Caches the responses of a query type, keyed by the query itself.
+///
+/// Expired responses are removed when they are asked again or when the cache is full. The cache
+/// is unbounded unless it is given a maximum number of entries, evicting the oldest one first.
+///
+pub struct CachingMiddleware<Q: Query> {
+    ttl: Option<Duration>,
+    max_entries: Option<usize>,
+    cache: Mutex<HashMap<Q, (Instant, Q::Response)>>
+}
+
+impl<Q> CachingMiddleware<Q>
+where
+    Q: Query + Hash + Eq + Clone,
+    Q::Response: Clone
+{
+    pub fn new() -> Self {
+        CachingMiddleware {
+            ttl: None,
+            max_entries: None,
+            cache: Mutex::new(HashMap::new())
+        }
+    }
+
+    ///
+    /// Responses older than `ttl` are computed again.
+    ///
+    pub fn with_ttl(ttl: Duration) -> Self {
+        CachingMiddleware {
+            ttl: Some(ttl),
+            max_entries: None,
+            cache: Mutex::new(HashMap::new())
+        }
+    }
+
+    ///
+    /// Keep at most `max_entries` responses, evicting the oldest one to make room for a new one.
+    ///
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    pub fn invalidate(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    fn cached(&self, query: &Q) -> Option<Q::Response> {
+        let mut cache = self.cache.lock().unwrap();
+        let (cached_at, response) = cache.get(query)?;
+
+        if self.is_expired(cached_at) {
+            cache.remove(query);
+            return None;
+        }
+
+        Some(response.clone())
+    }
+
+    fn store(&self, query: &Q, response: &Q::Response) {
+        let mut cache = self.cache.lock().unwrap();
+
+        if let Some(max_entries) = self.max_entries {
+            if !cache.contains_key(query) && cache.len() >= max_entries {
+                cache.retain(|_, (cached_at, _)| !self.is_expired(cached_at));
+            }
+
+            if !cache.contains_key(query) && cache.len() >= max_entries {
+                let oldest = cache.iter()
+                                  .min_by_key(|(_, (cached_at, _))| *cached_at)
+                                  .map(|(query, _)| query.clone());
+
+                if let Some(oldest) = oldest {
+                    cache.remove(&oldest);
+                }
+            }
+        }
+
+        cache.insert(query.clone(), (Instant::now(), response.clone()));
+    }
+
+    fn is_expired(&self, cached_at: &Instant) -> bool {
+        self.ttl.is_some_and(|ttl| cached_at.elapsed() >= ttl)
+    }
+}
+
+impl<Q> Default for CachingMiddleware<Q>
+where
+    Q: Query + Hash + Eq + Clone,
+    Q::Response: Clone
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Q> QueryMiddleware<Q> for CachingMiddleware<Q>
+where
+    Q: Query + Hash + Eq + Clone,
+    Q::Response: Clone
+{
+    fn handle(&self, query: &Q, next: &dyn Fn(&Q) -> Q::Response) -> Q::Response {
+        if let Some(response) = self.cached(query) {
+            return response;
+        }
+
+        let response = next(query);
+        self.store(query, &response);
+
+        response
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Q> AsyncQueryMiddleware<Q> for CachingMiddleware<Q>
+where
+    Q: Query + Hash + Eq + Clone,
+    Q::Response: Clone + Sync
+{
+    async fn handle(&self, query: Arc<Q>, next: QueryNext<Q>) -> Q::Response {
+        if let Some(response) = self.cached(&query) {
+            return response;
+        }
+
+        let response = next(query.clone()).await;
+        self.store(&query, &response);
+
+        response
+    }
+}
+
+///
+/// Logs how long every query takes to be answered.
+///
+pub struct TimingMiddleware;
+
+impl<Q: Query> QueryMiddleware<Q> for TimingMiddleware {
+    fn handle(&self, query: &Q, next: &dyn Fn(&Q) -> Q::Response) -> Q::Response {
+        let start = Instant::now();
+        let response = next(query);
+
+        log::debug!("Query {} answered in {:?}", type_name::<Q>(), start.elapsed());
+
+        response
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Q: Query> AsyncQueryMiddleware<Q> for TimingMiddleware {
+    async fn handle(&self, query: Arc<Q>, next: QueryNext<Q>) -> Q::Response {
+        let start = Instant::now();
+        let response = next(query).await;
+
+        log::debug!("Query {} answered in {:?}", type_name::<Q>(), start.elapsed());
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[derive(Clone, Hash, PartialEq, Eq)]
+    struct FindOrder {
+        id: u32
+    }
+
+    impl Query for FindOrder {
+        type Response = u32;
+    }
+
+    fn ask(caching: &CachingMiddleware<FindOrder>, calls: &Cell<u32>, id: u32) -> u32 {
+        QueryMiddleware::handle(caching, &FindOrder { id }, &|query: &FindOrder| {
+            calls.set(calls.get() + 1);
+            query.id
+        })
+    }
+
+    #[test]
+    fn it_should_remove_the_expired_responses_when_they_are_asked_again() {
+        let caching = CachingMiddleware::with_ttl(Duration::from_millis(10));
+        let calls = Cell::new(0);
+
+        ask(&caching, &calls, 1);
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(caching.cached(&FindOrder { id: 1 }).is_none());
+        assert!(caching.cache.lock().unwrap().is_empty());
+
+        ask(&caching, &calls, 1);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn it_should_evict_the_oldest_response_once_full() {
+        let caching = CachingMiddleware::new().with_max_entries(2);
+        let calls = Cell::new(0);
+
+        ask(&caching, &calls, 1);
+        std::thread::sleep(Duration::from_millis(1));
+        ask(&caching, &calls, 2);
+        ask(&caching, &calls, 3);
+        assert_eq!(caching.cache.lock().unwrap().len(), 2);
+        assert_eq!(calls.get(), 3);
+
+        ask(&caching, &calls, 3);
+        ask(&caching, &calls, 2);
+        assert_eq!(calls.get(), 3);
+
+        ask(&caching, &calls, 1);
+        assert_eq!(calls.get(), 4);
+    }
+}
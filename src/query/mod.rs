@@ -0,0 +1,76 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+
+pub mod middleware;
+pub mod synchronous_query_bus;
+
+#[cfg(feature = "async")]
+pub mod asynchronous_query_bus;
+
+///
+/// A request to read the state of the system, answered by exactly one handler.
+///
+pub trait Query: Send + Sync + 'static {
+    type Response: Send + 'static;
+}
+
+pub trait QueryHandler<Q: Query> {
+    fn handle(&self, query: &Q) -> Q::Response;
+}
+
+#[cfg(feature = "async")]
+pub trait AsyncQueryHandler<Q: Query>: Send + Sync + 'static {
+    fn handle(&self, query: &Q) -> impl Future<Output = Q::Response> + Send;
+}
+
+pub trait QueryBus {
+    fn ask<Q: Query>(&self, query: Q) -> Result<Q::Response, QueryBusError>;
+}
+
+#[cfg(feature = "async")]
+pub trait AsynchronousQueryBus {
+    fn ask<Q: Query>(&self, query: Q) -> impl Future<Output = Result<Q::Response, QueryBusError>> + Send;
+}
+
+///
+/// Wraps the handling of a query, calling `next` to continue with the rest of the pipeline.
+///
+pub trait QueryMiddleware<Q: Query> {
+    fn handle(&self, query: &Q, next: &dyn Fn(&Q) -> Q::Response) -> Q::Response;
+}
+
+///
+/// The rest of the pipeline of an asynchronous query.
+///
+#[cfg(feature = "async")]
+pub type QueryNext<Q> = Arc<dyn Fn(Arc<Q>) -> Pin<Box<dyn Future<Output = <Q as Query>::Response> + Send>> + Send + Sync>;
+
+#[cfg(feature = "async")]
+pub trait AsyncQueryMiddleware<Q: Query>: Send + Sync + 'static {
+    fn handle(&self, query: Arc<Q>, next: QueryNext<Q>) -> impl Future<Output = Q::Response> + Send;
+}
+
+#[derive(Debug)]
+pub enum QueryBusError {
+    HandlerAlreadyRegistered(&'static str),
+    HandlerNotFound(&'static str),
+    HandlerPanicked(&'static str),
+}
+
+impl Display for QueryBusError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryBusError::HandlerAlreadyRegistered(query) => write!(f, "A handler is already registered for {}", query),
+            QueryBusError::HandlerNotFound(query) => write!(f, "No handler registered for {}", query),
+            QueryBusError::HandlerPanicked(query) => write!(f, "The handler of {} panicked", query),
+        }
+    }
+}
+
+impl Error for QueryBusError {}
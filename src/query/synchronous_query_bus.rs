@@ -0,0 +1,129 @@
+use std::any::{Any, type_name, TypeId};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::query::{Query, QueryBus, QueryBusError, QueryHandler, QueryMiddleware};
+
+type HandlerClosure<Q> = Rc<dyn Fn(&Q) -> <Q as Query>::Response>;
+
+///
+/// A synchronous query bus that answers every query with its only handler.
+///
+#[derive(Default)]
+pub struct SynchronousQueryBus {
+    handlers: HashMap<TypeId, Box<dyn Any>>
+}
+
+impl SynchronousQueryBus {
+    pub fn new() -> Self {
+        SynchronousQueryBus {
+            handlers: HashMap::new()
+        }
+    }
+
+    pub fn register<Q, H>(&mut self, handler: Rc<H>) -> Result<(), QueryBusError>
+    where
+        Q: Query,
+        H: QueryHandler<Q> + 'static
+    {
+        match self.handlers.entry(TypeId::of::<Q>()) {
+            Entry::Occupied(_) => Err(QueryBusError::HandlerAlreadyRegistered(type_name::<Q>())),
+            Entry::Vacant(entry) => {
+                let closure: HandlerClosure<Q> = Rc::new(move |query| handler.handle(query));
+                entry.insert(Box::new(closure));
+
+                Ok(())
+            }
+        }
+    }
+
+    ///
+    /// Wrap the handler of a query type with a middleware.
+    ///
+    /// The handler must be registered first, and the last middleware added runs first.
+    ///
+    pub fn add_middleware<Q, M>(&mut self, middleware: Rc<M>) -> Result<(), QueryBusError>
+    where
+        Q: Query,
+        M: QueryMiddleware<Q> + 'static
+    {
+        let next = self.handler::<Q>()?.clone();
+        let closure: HandlerClosure<Q> = Rc::new(move |query| middleware.handle(query, next.as_ref()));
+
+        self.handlers.insert(TypeId::of::<Q>(), Box::new(closure));
+
+        Ok(())
+    }
+
+    fn handler<Q: Query>(&self) -> Result<&HandlerClosure<Q>, QueryBusError> {
+        self.handlers
+            .get(&TypeId::of::<Q>())
+            .and_then(|handler| handler.downcast_ref::<HandlerClosure<Q>>())
+            .ok_or(QueryBusError::HandlerNotFound(type_name::<Q>()))
+    }
+}
+
+impl QueryBus for SynchronousQueryBus {
+    fn ask<Q: Query>(&self, query: Q) -> Result<Q::Response, QueryBusError> {
+        let handler = self.handler::<Q>()?;
+
+        Ok(handler(&query))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use crate::query::middleware::{CachingMiddleware, TimingMiddleware};
+
+    use super::*;
+
+    #[derive(Clone, Hash, PartialEq, Eq)]
+    struct FindUserName {
+        id: u32
+    }
+
+    impl Query for FindUserName {
+        type Response = Option<String>;
+    }
+
+    struct UserNames {
+        calls: Cell<u32>
+    }
+
+    impl QueryHandler<FindUserName> for UserNames {
+        fn handle(&self, query: &FindUserName) -> Option<String> {
+            self.calls.set(self.calls.get() + 1);
+
+            match query.id {
+                1 => Some("user".to_string()),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_answer_queries_through_the_middlewares() {
+        let mut query_bus = SynchronousQueryBus::new();
+        let handler = Rc::new(UserNames { calls: Cell::new(0) });
+
+        query_bus.register(handler.clone()).unwrap();
+        query_bus.add_middleware::<FindUserName, _>(Rc::new(CachingMiddleware::new())).unwrap();
+        query_bus.add_middleware::<FindUserName, _>(Rc::new(TimingMiddleware)).unwrap();
+
+        assert_eq!(query_bus.ask(FindUserName { id: 1 }).unwrap(), Some("user".to_string()));
+        assert_eq!(query_bus.ask(FindUserName { id: 1 }).unwrap(), Some("user".to_string()));
+        assert_eq!(query_bus.ask(FindUserName { id: 2 }).unwrap(), None);
+        assert_eq!(handler.calls.get(), 2);
+    }
+
+    #[test]
+    fn it_should_fail_when_there_is_no_handler() {
+        let mut query_bus = SynchronousQueryBus::new();
+
+        assert!(matches!(query_bus.ask(FindUserName { id: 1 }), Err(QueryBusError::HandlerNotFound(_))));
+        assert!(matches!(query_bus.add_middleware::<FindUserName, _>(Rc::new(TimingMiddleware)), Err(QueryBusError::HandlerNotFound(_))));
+    }
+}
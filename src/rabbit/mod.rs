@@ -1,21 +1,70 @@
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 
+use crate::bus::error::PublishError;
+use crate::serializer::error::SerializeError;
+
 pub mod rabbit_channel;
 pub mod rabbit_publisher;
 pub mod rabbit_configurer;
+pub mod rabbit_rpc_client;
+
+///
+/// Header set on a reply when the server could not answer the request.
+///
+pub const RPC_ERROR_HEADER: &str = "x-rpc-error";
 
 #[derive(Debug)]
 pub enum RabbitError {
-    CannotOpenChannel,
+    CannotOpenChannel(lapin::Error),
 }
 
 impl Display for RabbitError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            RabbitError::CannotOpenChannel => write!(f, "Cannot open channel"),
+            RabbitError::CannotOpenChannel(error) => write!(f, "Cannot open channel: {}", error),
+        }
+    }
+}
+
+impl Error for RabbitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RabbitError::CannotOpenChannel(error) => Some(error),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RpcError {
+    CannotSerializeRequest(SerializeError),
+    CannotPublishRequest(PublishError),
+    CannotDeserializeResponse(Box<dyn Error + Send + Sync>),
+    ReplyQueueClosed,
+    Timeout,
+    Remote(String),
+}
+
+impl Display for RpcError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RpcError::CannotSerializeRequest(error) => write!(f, "Cannot serialize request: {}", error),
+            RpcError::CannotPublishRequest(error) => write!(f, "Cannot publish request: {}", error),
+            RpcError::CannotDeserializeResponse(error) => write!(f, "Cannot deserialize response: {}", error),
+            RpcError::ReplyQueueClosed => write!(f, "Reply queue closed before a response arrived"),
+            RpcError::Timeout => write!(f, "Timed out waiting for a response"),
+            RpcError::Remote(message) => write!(f, "Remote error: {}", message),
         }
     }
 }
 
-impl Error for RabbitError {}
\ No newline at end of file
+impl Error for RpcError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RpcError::CannotSerializeRequest(error) => Some(error),
+            RpcError::CannotPublishRequest(error) => Some(error),
+            RpcError::CannotDeserializeResponse(error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -22,7 +22,7 @@ impl RabbitChannel {
         let mut write_guard = self.channel.write().await;
         let channel = self.connection.create_channel()
                           .await
-                          .map_err(|_| RabbitError::CannotOpenChannel)?;
+                          .map_err(RabbitError::CannotOpenChannel)?;
 
         *write_guard = channel;
 
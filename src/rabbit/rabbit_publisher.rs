@@ -24,27 +24,14 @@ impl RabbitPublisher {
     }
 
     pub async fn publish(&self, payload: &[u8], routing_key: &str, exchange: &str) -> Result<(), PublishError> {
-        let channel = self.get_guard_channel().await?;
-
-        let publish_message = channel
-            .basic_publish(
-                exchange,
-                routing_key,
-                lapin::options::BasicPublishOptions::default(),
-                payload,
-                BasicProperties::default(),
-            );
-
-        publish_message
-            .await
-            .map_err(|_| PublishError::CannotOpenChannel)?
-            .await
-            .map_err(|_| PublishError::CannotPublishEvent)?;
-
-        Ok(())
+        self.publish_with_properties(payload, routing_key, exchange, BasicProperties::default()).await
     }
 
     pub async fn publish_with_headers(&self, payload: &[u8], routing_key: &str, exchange: &str, headers: FieldTable) -> Result<(), PublishError> {
+        self.publish_with_properties(payload, routing_key, exchange, BasicProperties::default().with_headers(headers)).await
+    }
+
+    pub async fn publish_with_properties(&self, payload: &[u8], routing_key: &str, exchange: &str, properties: BasicProperties) -> Result<(), PublishError> {
         let channel = self.get_guard_channel().await?;
 
         let publish_message = channel
@@ -53,15 +40,14 @@ impl RabbitPublisher {
                 routing_key,
                 lapin::options::BasicPublishOptions::default(),
                 payload,
-                BasicProperties::default()
-                    .with_headers(headers),
+                properties,
             );
 
         publish_message
             .await
-            .map_err(|_| PublishError::CannotOpenChannel)?
+            .map_err(|e| PublishError::CannotOpenChannel(Box::new(e)))?
             .await
-            .map_err(|_| PublishError::CannotPublishEvent)?;
+            .map_err(|e| PublishError::CannotPublishEvent(Box::new(e)))?;
 
         Ok(())
     }
@@ -69,6 +55,6 @@ impl RabbitPublisher {
     async fn get_guard_channel(&self) -> Result<RwLockReadGuard<Channel>, PublishError> {
         self.channel.get_guard_channel()
             .await
-            .map_err(|_| PublishError::CannotOpenChannel)
+            .map_err(|e| PublishError::CannotOpenChannel(Box::new(e)))
     }
 }
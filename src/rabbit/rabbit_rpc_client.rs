@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use futures_lite::Stream;
+use futures_lite::stream::StreamExt;
+use lapin::{BasicProperties, Channel, Connection};
+use lapin::message::Delivery;
+use lapin::options::{BasicConsumeOptions, BasicPublishOptions, QueueDeclareOptions};
+use lapin::types::{AMQPValue, FieldTable, ShortString};
+use log::error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::bus::error::PublishError;
+use crate::event::{Event, EventWithMetadata};
+use crate::rabbit::{RPC_ERROR_HEADER, RpcError};
+use crate::rabbit::rabbit_publisher::RabbitPublisher;
+use crate::serializer::{EventDeserializer, EventSerializer};
+
+///
+/// The pseudo-queue of RabbitMQ direct reply-to.
+///
+pub const DIRECT_REPLY_TO: &str = "amq.rabbitmq.reply-to";
+
+///
+/// Where the responses of a `RabbitRpcClient` arrive.
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyQueue {
+    ///
+    /// An exclusive, server-named queue owned by the client.
+    ///
+    #[default]
+    Exclusive,
+    ///
+    /// RabbitMQ direct reply-to, without declaring any queue. Requests are then published on the
+    /// channel of the client instead of through the publisher, as direct reply-to requires.
+    ///
+    DirectReplyTo,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Reply {
+    Payload(Vec<u8>),
+    Error(String),
+}
+
+#[derive(Default)]
+struct Pending {
+    senders: HashMap<String, oneshot::Sender<Reply>>,
+    closed: bool,
+}
+
+///
+/// The requests waiting for their reply, by correlation id.
+///
+#[derive(Default, Clone)]
+struct PendingReplies(Arc<Mutex<Pending>>);
+
+impl PendingReplies {
+    ///
+    /// Wait for the reply of a request. Once the replies are closed, the receiver fails right away.
+    ///
+    fn register(&self, correlation_id: &str) -> oneshot::Receiver<Reply> {
+        let (sender, receiver) = oneshot::channel();
+        let mut pending = self.0.lock().unwrap();
+        if !pending.closed {
+            pending.senders.insert(correlation_id.to_string(), sender);
+        }
+
+        receiver
+    }
+
+    ///
+    /// Hand the reply to the request waiting for it, returning false when there is none.
+    ///
+    fn resolve(&self, correlation_id: &str, reply: Reply) -> bool {
+        let sender = self.0.lock().unwrap().senders.remove(correlation_id);
+
+        match sender {
+            Some(sender) => {
+                let _ = sender.send(reply);
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn cancel(&self, correlation_id: &str) {
+        self.0.lock().unwrap().senders.remove(correlation_id);
+    }
+
+    ///
+    /// Fail the pending requests and the ones to come with `RpcError::ReplyQueueClosed`.
+    ///
+    fn close(&self) {
+        let mut pending = self.0.lock().unwrap();
+        pending.closed = true;
+        pending.senders.clear();
+    }
+
+    ///
+    /// Wait for the reply of the request, forgetting it when the timeout elapses.
+    ///
+    async fn wait(&self, correlation_id: &str, receiver: oneshot::Receiver<Reply>, timeout: Duration) -> Result<Reply, RpcError> {
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(reply)) => Ok(reply),
+            Ok(Err(_)) => Err(RpcError::ReplyQueueClosed),
+            Err(_) => {
+                self.cancel(correlation_id);
+                Err(RpcError::Timeout)
+            }
+        }
+    }
+}
+
+///
+/// Publishes requests with `reply_to` and `correlation_id` and waits for the typed response.
+///
+/// Responses arrive on an exclusive, server-named queue owned by the client, or through direct reply-to.
+/// They are received by a task that lives as long as the client.
+///
+pub struct RabbitRpcClient<'a, S: EventSerializer, D: EventDeserializer> {
+    publisher: &'a RabbitPublisher,
+    serializer: &'a S,
+    deserializer: &'a D,
+    timeout: Duration,
+    reply_queue: String,
+    direct_reply_to: bool,
+    next_correlation_id: AtomicU64,
+    pending: PendingReplies,
+    channel: Channel,
+    replies: JoinHandle<()>,
+}
+
+impl<'a, S: EventSerializer, D: EventDeserializer> RabbitRpcClient<'a, S, D> {
+    pub async fn new(
+        connection: Arc<Connection>,
+        publisher: &'a RabbitPublisher,
+        serializer: &'a S,
+        deserializer: &'a D,
+        timeout: Duration
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::with_reply_queue(connection, publisher, serializer, deserializer, timeout, ReplyQueue::Exclusive).await
+    }
+
+    ///
+    /// Like `new`, receiving the responses through the given reply queue.
+    ///
+    pub async fn with_reply_queue(
+        connection: Arc<Connection>,
+        publisher: &'a RabbitPublisher,
+        serializer: &'a S,
+        deserializer: &'a D,
+        timeout: Duration,
+        reply_queue: ReplyQueue
+    ) -> Result<Self, Box<dyn Error>> {
+        let channel = connection.create_channel().await?;
+        let reply_queue_name = match reply_queue {
+            ReplyQueue::DirectReplyTo => DIRECT_REPLY_TO.to_string(),
+            ReplyQueue::Exclusive => {
+                let queue = channel
+                    .queue_declare(
+                        "",
+                        QueueDeclareOptions {
+                            exclusive: true,
+                            auto_delete: true,
+                            ..QueueDeclareOptions::default()
+                        },
+                        FieldTable::default(),
+                    )
+                    .await?;
+
+                queue.name().as_str().to_string()
+            }
+        };
+
+        let consumer = channel
+            .basic_consume(
+                &reply_queue_name,
+                "",
+                BasicConsumeOptions {
+                    no_ack: true,
+                    ..BasicConsumeOptions::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+
+        let pending = PendingReplies::default();
+        let replies = tokio::spawn(receive_replies(consumer, pending.clone()));
+
+        Ok(
+            Self {
+                publisher,
+                serializer,
+                deserializer,
+                timeout,
+                reply_queue: reply_queue_name,
+                direct_reply_to: reply_queue == ReplyQueue::DirectReplyTo,
+                next_correlation_id: AtomicU64::new(1),
+                pending,
+                channel,
+                replies,
+            }
+        )
+    }
+
+    pub async fn call<Req, Res>(&self, request: &Req, routing_key: &str, exchange: &str) -> Result<Res, RpcError>
+    where
+        Req: Event + EventWithMetadata + Serialize,
+        Res: Serialize + DeserializeOwned
+    {
+        let payload = self.serializer
+                          .serialize(request)
+                          .map_err(RpcError::CannotSerializeRequest)?;
+
+        let correlation_id = self.next_correlation_id.fetch_add(1, Ordering::Relaxed).to_string();
+        let receiver = self.pending.register(&correlation_id);
+
+        let properties = BasicProperties::default()
+            .with_content_type(ShortString::from(self.serializer.content_type()))
+            .with_reply_to(ShortString::from(self.reply_queue.clone()))
+            .with_correlation_id(ShortString::from(correlation_id.clone()));
+
+        if let Err(error) = self.publish(&payload, routing_key, exchange, properties).await {
+            self.pending.cancel(&correlation_id);
+            return Err(RpcError::CannotPublishRequest(error));
+        }
+
+        match self.pending.wait(&correlation_id, receiver, self.timeout).await? {
+            Reply::Error(message) => Err(RpcError::Remote(message)),
+            Reply::Payload(payload) => {
+                self.deserializer
+                    .deserialize::<Res>(&payload)
+                    .map(|response| response.data.attributes)
+                    .map_err(|e| RpcError::CannotDeserializeResponse(Box::new(e)))
+            }
+        }
+    }
+
+    async fn publish(&self, payload: &[u8], routing_key: &str, exchange: &str, properties: BasicProperties) -> Result<(), PublishError> {
+        if !self.direct_reply_to {
+            return self.publisher.publish_with_properties(payload, routing_key, exchange, properties).await;
+        }
+
+        self.channel
+            .basic_publish(exchange, routing_key, BasicPublishOptions::default(), payload, properties)
+            .await
+            .map_err(|e| PublishError::CannotOpenChannel(Box::new(e)))?
+            .await
+            .map_err(|e| PublishError::CannotPublishEvent(Box::new(e)))?;
+
+        Ok(())
+    }
+}
+
+impl<S: EventSerializer, D: EventDeserializer> Drop for RabbitRpcClient<'_, S, D> {
+    fn drop(&mut self) {
+        self.replies.abort();
+    }
+}
+
+///
+/// Hand the replies to the requests waiting for them, failing the pending requests once the
+/// consumer ends.
+///
+async fn receive_replies(mut deliveries: impl Stream<Item = lapin::Result<Delivery>> + Unpin, replies: PendingReplies) {
+    while let Some(delivery) = deliveries.next().await {
+        let Ok(delivery) = delivery else {
+            continue;
+        };
+
+        let Some(correlation_id) = delivery.properties.correlation_id() else {
+            error!("Received a reply without correlation id");
+            continue;
+        };
+
+        let correlation_id = correlation_id.to_string();
+        if !replies.resolve(&correlation_id, reply_from(&delivery.properties, delivery.data)) {
+            error!("Received a reply for an unknown request {}", correlation_id);
+        }
+    }
+
+    error!("The reply consumer ended, failing the pending requests");
+    replies.close();
+}
+
+fn reply_from(properties: &BasicProperties, data: Vec<u8>) -> Reply {
+    let error = properties.headers()
+                          .as_ref()
+                          .and_then(|headers| headers.inner().get(&ShortString::from(RPC_ERROR_HEADER)).cloned());
+
+    match error {
+        Some(AMQPValue::LongString(message)) => Reply::Error(String::from_utf8_lossy(message.as_bytes()).to_string()),
+        Some(_) => Reply::Error("Unknown error".to_string()),
+        None => Reply::Payload(data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lapin::types::LongString;
+
+    use super::*;
+
+    #[test]
+    fn it_should_read_the_payload_or_the_remote_error_of_a_reply() {
+        let mut headers = FieldTable::default();
+        headers.insert(ShortString::from(RPC_ERROR_HEADER), AMQPValue::LongString(LongString::from("Unknown account")));
+
+        assert_eq!(reply_from(&BasicProperties::default(), b"{}".to_vec()), Reply::Payload(b"{}".to_vec()));
+        assert_eq!(reply_from(&BasicProperties::default().with_headers(headers), vec![]), Reply::Error("Unknown account".to_string()));
+    }
+
+    #[tokio::test]
+    async fn it_should_hand_the_reply_to_the_request_with_its_correlation_id() {
+        let pending = PendingReplies::default();
+        let receiver = pending.register("1");
+
+        assert!(!pending.resolve("2", Reply::Payload(vec![])));
+        assert!(pending.resolve("1", Reply::Payload(b"{}".to_vec())));
+
+        let reply = pending.wait("1", receiver, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(reply, Reply::Payload(b"{}".to_vec()));
+        assert!(pending.0.lock().unwrap().senders.is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_should_forget_the_request_when_its_reply_times_out() {
+        let pending = PendingReplies::default();
+        let receiver = pending.register("1");
+
+        let Err(error) = pending.wait("1", receiver, Duration::from_millis(10)).await else {
+            panic!("The request should time out");
+        };
+
+        assert!(matches!(error, RpcError::Timeout));
+        assert!(pending.0.lock().unwrap().senders.is_empty());
+        assert!(!pending.resolve("1", Reply::Payload(vec![])));
+    }
+
+    #[tokio::test]
+    async fn it_should_fail_the_pending_requests_once_the_reply_consumer_ends() {
+        let pending = PendingReplies::default();
+        let receiver = pending.register("1");
+
+        receive_replies(futures_lite::stream::empty(), pending.clone()).await;
+
+        let waited = pending.wait("1", receiver, Duration::from_secs(1)).await;
+        assert!(matches!(waited, Err(RpcError::ReplyQueueClosed)));
+
+        let receiver = pending.register("2");
+        let waited = pending.wait("2", receiver, Duration::from_secs(1)).await;
+        assert!(matches!(waited, Err(RpcError::ReplyQueueClosed)));
+    }
+}
@@ -0,0 +1,117 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::bus::AsynchronousEventBus;
+use crate::bus::error::PublishError;
+use crate::event::{Event, EventWithMetadata};
+use crate::event_store::ExpectedVersion;
+use crate::serializer::error::{DeserializeError, SerializeError};
+use crate::subscriber::SubscriberError;
+
+pub mod saga_store;
+pub mod saga_manager;
+
+pub use crate::event::CORRELATION_ID;
+
+///
+/// A long-running process coordinated through events.
+///
+/// The state of every running instance is persisted between events, and each
+/// event is delivered through the `SagaHandler` implementation for its type.
+///
+pub trait Saga: Send + Sync + 'static {
+    type State: Serialize + DeserializeOwned + Default + Send + Sync;
+
+    fn saga_name(&self) -> &'static str;
+
+    ///
+    /// Time an instance has to complete before it is compensated.
+    ///
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    ///
+    /// Publish the compensating events of an instance that has to be undone.
+    ///
+    fn compensate<B: AsynchronousEventBus + Send + Sync>(&self, state: &Self::State, context: &SagaContext<'_, B>) -> impl Future<Output = Result<(), SubscriberError>> + Send;
+}
+
+pub trait SagaHandler<E: Event + EventWithMetadata>: Saga {
+    fn handle<B: AsynchronousEventBus + Send + Sync>(&self, state: &mut Self::State, event: &E, context: &SagaContext<'_, B>) -> impl Future<Output = Result<SagaStep, SubscriberError>> + Send;
+}
+
+///
+/// What to do with a saga instance after handling an event.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SagaStep {
+    Continue,
+    Complete,
+    Compensate,
+}
+
+///
+/// Gives a saga access to the event bus, stamping the correlation id on every published event.
+///
+pub struct SagaContext<'a, B: AsynchronousEventBus> {
+    bus: &'a B,
+    correlation_id: &'a str,
+}
+
+impl<'a, B: AsynchronousEventBus> SagaContext<'a, B> {
+    pub fn new(bus: &'a B, correlation_id: &'a str) -> Self {
+        Self {
+            bus,
+            correlation_id
+        }
+    }
+
+    pub fn correlation_id(&self) -> &str {
+        self.correlation_id
+    }
+
+    pub async fn publish<E: Event + EventWithMetadata + Serialize>(&self, mut event: E) -> Result<(), PublishError> {
+        if event.get_metadata(CORRELATION_ID).is_none() {
+            event.add_metadata(CORRELATION_ID.to_string(), self.correlation_id.to_string());
+        }
+
+        self.bus.publish(event).await
+    }
+}
+
+#[derive(Debug)]
+pub enum SagaError {
+    WrongExpectedVersion { saga_name: String, correlation_id: String, expected: ExpectedVersion, actual: u64 },
+    StorageError(String),
+    CannotSerializeState(SerializeError),
+    CannotDeserializeState(DeserializeError),
+}
+
+impl Display for SagaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SagaError::WrongExpectedVersion { saga_name, correlation_id, expected, actual } => {
+                write!(f, "Wrong expected version for saga {} of {}: expected {:?}, actual {}", saga_name, correlation_id, expected, actual)
+            },
+            SagaError::StorageError(error) => write!(f, "StorageError: {}", error),
+            SagaError::CannotSerializeState(error) => write!(f, "Cannot serialize saga state: {}", error),
+            SagaError::CannotDeserializeState(error) => write!(f, "Cannot deserialize saga state: {}", error),
+        }
+    }
+}
+
+impl Error for SagaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SagaError::CannotSerializeState(error) => Some(error),
+            SagaError::CannotDeserializeState(error) => Some(error),
+            SagaError::WrongExpectedVersion { .. } | SagaError::StorageError(_) => None,
+        }
+    }
+}
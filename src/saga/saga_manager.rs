@@ -0,0 +1,422 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::bus::AsynchronousEventBus;
+use crate::event::{Event, EventWithMetadata};
+use crate::event_store::ExpectedVersion;
+use crate::saga::{CORRELATION_ID, Saga, SagaContext, SagaError, SagaHandler, SagaStep};
+use crate::saga::saga_store::{SagaInstance, SagaStatus, SagaStore};
+use crate::serializer::serde_formatter::SerdeJSONEventFormatter;
+use crate::serializer::SnapshotSerializer;
+use crate::subscriber::{AsyncSubscriber, SubscriberError};
+
+///
+/// Runs a saga: it loads the instance correlated with every incoming event, delivers
+/// the event to the saga and persists the resulting state.
+///
+/// Instances are saved at the version they were loaded at. When another event of the same
+/// instance was handled in the meantime, the event fails with a retryable error so that it is
+/// handled again from the latest state.
+///
+/// Register it in an event bus or a consumer as any other `AsyncSubscriber`.
+///
+pub struct SagaManager<S, St, B, F = SerdeJSONEventFormatter>
+where
+    S: Saga,
+    St: SagaStore,
+    B: AsynchronousEventBus + Send + Sync,
+    F: SnapshotSerializer
+{
+    saga: S,
+    store: Arc<St>,
+    bus: Arc<B>,
+    serializer: Arc<F>,
+}
+
+impl<S, St, B> SagaManager<S, St, B>
+where
+    S: Saga,
+    St: SagaStore + 'static,
+    B: AsynchronousEventBus + Send + Sync + 'static
+{
+    pub fn new(saga: S, store: Arc<St>, bus: Arc<B>) -> Self {
+        Self::with_serializer(saga, store, bus, Arc::new(SerdeJSONEventFormatter))
+    }
+}
+
+impl<S, St, B, F> SagaManager<S, St, B, F>
+where
+    S: Saga,
+    St: SagaStore + 'static,
+    B: AsynchronousEventBus + Send + Sync + 'static,
+    F: SnapshotSerializer
+{
+    ///
+    /// Like `new`, serializing the state of the instances with the given serializer.
+    ///
+    pub fn with_serializer(saga: S, store: Arc<St>, bus: Arc<B>, serializer: Arc<F>) -> Self {
+        Self {
+            saga,
+            store,
+            bus,
+            serializer
+        }
+    }
+
+    ///
+    /// Compensate every running instance whose timeout has expired, returning how many were compensated.
+    ///
+    /// An instance that cannot be compensated, e.g. because an event saved it in the meantime, is
+    /// logged and skipped, to be checked again the next time.
+    ///
+    pub async fn check_timeouts(&self) -> Result<usize, SubscriberError> {
+        let expired = self.store
+                          .expired(self.saga.saga_name(), SystemTime::now())
+                          .map_err(|e| SubscriberError::Inner(Box::new(e)))?;
+        let mut compensated = 0;
+
+        for instance in expired {
+            let correlation_id = instance.correlation_id.clone();
+            log::warn!("Saga {} timed out for {}", instance.saga_name, correlation_id);
+
+            match self.time_out(instance).await {
+                Ok(()) => compensated += 1,
+                Err(e) => log::error!("Cannot compensate saga {} for {}: {}", self.saga.saga_name(), correlation_id, e),
+            }
+        }
+
+        Ok(compensated)
+    }
+
+    ///
+    /// Check the timeouts periodically, logging the errors and checking again after the interval.
+    ///
+    pub async fn run_timeouts(&self, interval: Duration) {
+        loop {
+            if let Err(e) = self.check_timeouts().await {
+                log::error!("Cannot check the timeouts of saga {}: {}", self.saga.saga_name(), e);
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn time_out(&self, mut instance: SagaInstance) -> Result<(), SubscriberError> {
+        let state = self.deserialize_state(&instance)?;
+        let context = SagaContext::new(self.bus.as_ref(), instance.correlation_id.as_str());
+        self.saga.compensate(&state, &context).await?;
+
+        instance.status = SagaStatus::Compensated;
+        self.save(instance)
+    }
+
+    fn start(&self, correlation_id: &str) -> Result<SagaInstance, SagaError> {
+        let state = self.serializer
+                        .serialize_snapshot(&S::State::default())
+                        .map_err(SagaError::CannotSerializeState)?;
+
+        Ok(SagaInstance {
+            saga_name: self.saga.saga_name().to_string(),
+            correlation_id: correlation_id.to_string(),
+            state,
+            status: SagaStatus::Running,
+            deadline: self.saga.timeout().map(|timeout| SystemTime::now() + timeout),
+            version: 0,
+        })
+    }
+
+    fn deserialize_state(&self, instance: &SagaInstance) -> Result<S::State, SubscriberError> {
+        self.serializer
+            .deserialize_snapshot(&instance.state)
+            .map_err(|e| SubscriberError::Inner(Box::new(SagaError::CannotDeserializeState(e))))
+    }
+
+    ///
+    /// Save the instance at the version it was loaded at, retrying the event when it is stale.
+    ///
+    fn save(&self, instance: SagaInstance) -> Result<(), SubscriberError> {
+        let expected_version = ExpectedVersion::Exact(instance.version);
+
+        self.store.save(instance, expected_version).map_err(|e| match e {
+            SagaError::WrongExpectedVersion { .. } => SubscriberError::retryable(e),
+            e => SubscriberError::Inner(Box::new(e)),
+        })
+    }
+}
+
+impl<E, S, St, B, F> AsyncSubscriber<E> for SagaManager<S, St, B, F>
+where
+    E: Event + EventWithMetadata,
+    S: SagaHandler<E>,
+    St: SagaStore + 'static,
+    B: AsynchronousEventBus + Send + Sync + 'static,
+    F: SnapshotSerializer
+{
+    async fn handle_event(&self, event: &E) -> Result<(), SubscriberError> {
+        let correlation_id = match event.get_metadata(CORRELATION_ID) {
+            Some(correlation_id) => correlation_id.clone(),
+            None => {
+                log::warn!("Event {} has no {} for saga {}", event.event_name(), CORRELATION_ID, self.saga.saga_name());
+                return Ok(());
+            }
+        };
+
+        let loaded = self.store.load(self.saga.saga_name(), &correlation_id);
+        let mut instance = match loaded {
+            Ok(Some(instance)) => instance,
+            Ok(None) => self.start(&correlation_id).map_err(|e| SubscriberError::Inner(Box::new(e)))?,
+            Err(e) => return Err(SubscriberError::Inner(Box::new(e))),
+        };
+
+        if instance.status != SagaStatus::Running {
+            return Ok(());
+        }
+
+        let mut state = self.deserialize_state(&instance)?;
+        let context = SagaContext::new(self.bus.as_ref(), correlation_id.as_str());
+
+        let step = self.saga.handle(&mut state, event, &context).await?;
+
+        instance.status = match step {
+            SagaStep::Continue => SagaStatus::Running,
+            SagaStep::Complete => SagaStatus::Completed,
+            SagaStep::Compensate => {
+                self.saga.compensate(&state, &context).await?;
+                SagaStatus::Compensated
+            }
+        };
+
+        instance.state = self.serializer
+                             .serialize_snapshot(&state)
+                             .map_err(|e| SubscriberError::Inner(Box::new(SagaError::CannotSerializeState(e))))?;
+
+        self.save(instance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::bus::asynchronous_bus::TokioEventBus;
+    use crate::event::EventMetadata;
+    use crate::saga::saga_store::InMemorySagaStore;
+
+    use super::*;
+
+    macro_rules! test_event {
+        ($event_name:ident, $name:literal) => {
+            #[derive(Serialize, Deserialize, Default)]
+            struct $event_name {
+                metadata: EventMetadata
+            }
+
+            impl Event for $event_name {
+                fn event_name(&self) -> &'static str {
+                    $name
+                }
+            }
+
+            crate::event_metadata!($event_name);
+        };
+    }
+
+    test_event!(OrderPlaced, "order_placed");
+    test_event!(PaymentFailed, "payment_failed");
+    test_event!(ChargePayment, "charge_payment");
+    test_event!(CancelOrder, "cancel_order");
+
+    #[derive(Serialize, Deserialize, Default)]
+    struct OrderState {
+        placed: bool
+    }
+
+    struct OrderSaga {
+        timeout: Option<Duration>
+    }
+
+    impl Saga for OrderSaga {
+        type State = OrderState;
+
+        fn saga_name(&self) -> &'static str {
+            "order_saga"
+        }
+
+        fn timeout(&self) -> Option<Duration> {
+            self.timeout
+        }
+
+        async fn compensate<B: AsynchronousEventBus + Send + Sync>(&self, _state: &OrderState, context: &SagaContext<'_, B>) -> Result<(), SubscriberError> {
+            let _ = context.publish(CancelOrder::default()).await;
+            Ok(())
+        }
+    }
+
+    impl SagaHandler<OrderPlaced> for OrderSaga {
+        async fn handle<B: AsynchronousEventBus + Send + Sync>(&self, state: &mut OrderState, _event: &OrderPlaced, context: &SagaContext<'_, B>) -> Result<SagaStep, SubscriberError> {
+            state.placed = true;
+            let _ = context.publish(ChargePayment::default()).await;
+            Ok(SagaStep::Continue)
+        }
+    }
+
+    impl SagaHandler<PaymentFailed> for OrderSaga {
+        async fn handle<B: AsynchronousEventBus + Send + Sync>(&self, _state: &mut OrderState, _event: &PaymentFailed, _context: &SagaContext<'_, B>) -> Result<SagaStep, SubscriberError> {
+            Ok(SagaStep::Compensate)
+        }
+    }
+
+    #[derive(Default)]
+    struct PublishedEvents {
+        events: Mutex<Vec<(String, String)>>
+    }
+
+    impl PublishedEvents {
+        fn record<E: Event + EventWithMetadata>(&self, event: &E) {
+            let correlation_id = event.get_metadata(CORRELATION_ID).cloned().unwrap_or_default();
+            self.events.lock().unwrap().push((event.event_name().to_string(), correlation_id));
+        }
+    }
+
+    impl AsyncSubscriber<ChargePayment> for PublishedEvents {
+        async fn handle_event(&self, event: &ChargePayment) -> Result<(), SubscriberError> {
+            self.record(event);
+            Ok(())
+        }
+    }
+
+    impl AsyncSubscriber<CancelOrder> for PublishedEvents {
+        async fn handle_event(&self, event: &CancelOrder) -> Result<(), SubscriberError> {
+            self.record(event);
+            Ok(())
+        }
+    }
+
+    fn correlated<E: EventWithMetadata + Default>(correlation_id: &str) -> E {
+        let mut event = E::default();
+        event.add_metadata(CORRELATION_ID.to_string(), correlation_id.to_string());
+        event
+    }
+
+    fn bus_with(published: Arc<PublishedEvents>) -> Arc<TokioEventBus> {
+        let event_bus = TokioEventBus::default();
+        event_bus.register::<ChargePayment, _>(published.clone());
+        event_bus.register::<CancelOrder, _>(published);
+
+        Arc::new(event_bus)
+    }
+
+    #[tokio::test]
+    async fn it_should_dispatch_follow_up_and_compensating_events_with_the_correlation_id() {
+        let published = Arc::new(PublishedEvents::default());
+        let store = Arc::new(InMemorySagaStore::new());
+        let manager = SagaManager::new(OrderSaga { timeout: None }, store.clone(), bus_with(published.clone()));
+
+        manager.handle_event(&correlated::<OrderPlaced>("order-1")).await.unwrap();
+        manager.handle_event(&correlated::<PaymentFailed>("order-1")).await.unwrap();
+        manager.handle_event(&OrderPlaced::default()).await.unwrap();
+
+        let events = published.events.lock().unwrap().clone();
+        assert_eq!(events, vec![
+            ("charge_payment".to_string(), "order-1".to_string()),
+            ("cancel_order".to_string(), "order-1".to_string()),
+        ]);
+
+        let instance = store.load("order_saga", "order-1").unwrap().unwrap();
+        assert_eq!(instance.status, SagaStatus::Compensated);
+        assert_eq!(instance.state, b"{\"placed\":true}");
+        assert_eq!(instance.version, 2);
+    }
+
+    struct ConcurrentlySavedStore {
+        inner: InMemorySagaStore,
+    }
+
+    impl SagaStore for ConcurrentlySavedStore {
+        fn load(&self, saga_name: &str, correlation_id: &str) -> Result<Option<SagaInstance>, SagaError> {
+            let loaded = self.inner.load(saga_name, correlation_id)?;
+
+            if let Some(instance) = loaded.clone() {
+                let version = instance.version;
+                self.inner.save(instance, ExpectedVersion::Exact(version))?;
+            }
+
+            Ok(loaded)
+        }
+
+        fn save(&self, instance: SagaInstance, expected_version: ExpectedVersion) -> Result<(), SagaError> {
+            self.inner.save(instance, expected_version)
+        }
+
+        fn expired(&self, saga_name: &str, now: SystemTime) -> Result<Vec<SagaInstance>, SagaError> {
+            self.inner.expired(saga_name, now)
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_retry_an_event_whose_instance_was_saved_in_the_meantime() {
+        let published = Arc::new(PublishedEvents::default());
+        let store = Arc::new(ConcurrentlySavedStore { inner: InMemorySagaStore::new() });
+        let manager = SagaManager::new(OrderSaga { timeout: None }, store.clone(), bus_with(published));
+
+        manager.handle_event(&correlated::<OrderPlaced>("order-1")).await.unwrap();
+        let result = manager.handle_event(&correlated::<PaymentFailed>("order-1")).await;
+
+        assert!(matches!(result, Err(SubscriberError::Retryable { .. })));
+        assert_eq!(store.inner.load("order_saga", "order-1").unwrap().unwrap().status, SagaStatus::Running);
+    }
+
+    #[tokio::test]
+    async fn it_should_compensate_expired_sagas() {
+        let published = Arc::new(PublishedEvents::default());
+        let store = Arc::new(InMemorySagaStore::new());
+        let manager = SagaManager::new(OrderSaga { timeout: Some(Duration::ZERO) }, store.clone(), bus_with(published.clone()));
+
+        manager.handle_event(&correlated::<OrderPlaced>("order-1")).await.unwrap();
+
+        assert_eq!(manager.check_timeouts().await.unwrap(), 1);
+        assert_eq!(manager.check_timeouts().await.unwrap(), 0);
+        assert_eq!(published.events.lock().unwrap().len(), 2);
+        assert_eq!(store.load("order_saga", "order-1").unwrap().unwrap().status, SagaStatus::Compensated);
+    }
+
+    struct StaleExpiredStore {
+        inner: InMemorySagaStore,
+        stale: &'static str,
+    }
+
+    impl SagaStore for StaleExpiredStore {
+        fn load(&self, saga_name: &str, correlation_id: &str) -> Result<Option<SagaInstance>, SagaError> {
+            self.inner.load(saga_name, correlation_id)
+        }
+
+        fn save(&self, instance: SagaInstance, expected_version: ExpectedVersion) -> Result<(), SagaError> {
+            self.inner.save(instance, expected_version)
+        }
+
+        fn expired(&self, saga_name: &str, now: SystemTime) -> Result<Vec<SagaInstance>, SagaError> {
+            let mut expired = self.inner.expired(saga_name, now)?;
+            for instance in expired.iter_mut().filter(|instance| instance.correlation_id == self.stale) {
+                instance.version -= 1;
+            }
+
+            Ok(expired)
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_skip_the_expired_sagas_that_cannot_be_compensated() {
+        let published = Arc::new(PublishedEvents::default());
+        let store = Arc::new(StaleExpiredStore { inner: InMemorySagaStore::new(), stale: "order-1" });
+        let manager = SagaManager::new(OrderSaga { timeout: Some(Duration::ZERO) }, store.clone(), bus_with(published));
+
+        manager.handle_event(&correlated::<OrderPlaced>("order-1")).await.unwrap();
+        manager.handle_event(&correlated::<OrderPlaced>("order-2")).await.unwrap();
+
+        assert_eq!(manager.check_timeouts().await.unwrap(), 1);
+        assert_eq!(store.load("order_saga", "order-1").unwrap().unwrap().status, SagaStatus::Running);
+        assert_eq!(store.load("order_saga", "order-2").unwrap().unwrap().status, SagaStatus::Compensated);
+    }
+}
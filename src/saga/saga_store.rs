@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use crate::event_store::ExpectedVersion;
+use crate::saga::SagaError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SagaStatus {
+    Running,
+    Completed,
+    Compensated,
+}
+
+///
+/// The persisted state of a saga for a correlation id.
+///
+#[derive(Debug, Clone)]
+pub struct SagaInstance {
+    pub saga_name: String,
+    pub correlation_id: String,
+    pub state: Vec<u8>,
+    pub status: SagaStatus,
+    pub deadline: Option<SystemTime>,
+    ///
+    /// How many times the instance was saved, 0 until it is saved the first time.
+    ///
+    pub version: u64,
+}
+
+pub trait SagaStore: Send + Sync {
+    fn load(&self, saga_name: &str, correlation_id: &str) -> Result<Option<SagaInstance>, SagaError>;
+
+    ///
+    /// Save the instance if the stored one is at the expected version, bumping its version, and
+    /// fail with `SagaError::WrongExpectedVersion` otherwise.
+    ///
+    fn save(&self, instance: SagaInstance, expected_version: ExpectedVersion) -> Result<(), SagaError>;
+
+    ///
+    /// The running instances of a saga whose deadline is before `now`.
+    ///
+    fn expired(&self, saga_name: &str, now: SystemTime) -> Result<Vec<SagaInstance>, SagaError>;
+}
+
+#[derive(Default)]
+pub struct InMemorySagaStore {
+    instances: RwLock<HashMap<(String, String), SagaInstance>>,
+}
+
+impl InMemorySagaStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SagaStore for InMemorySagaStore {
+    fn load(&self, saga_name: &str, correlation_id: &str) -> Result<Option<SagaInstance>, SagaError> {
+        let instances = self.instances.read().map_err(|e| SagaError::StorageError(e.to_string()))?;
+
+        Ok(instances.get(&(saga_name.to_string(), correlation_id.to_string())).cloned())
+    }
+
+    fn save(&self, instance: SagaInstance, expected_version: ExpectedVersion) -> Result<(), SagaError> {
+        let mut instances = self.instances.write().map_err(|e| SagaError::StorageError(e.to_string()))?;
+        let key = (instance.saga_name.clone(), instance.correlation_id.clone());
+
+        let current_version = instances.get(&key).map_or(0, |stored| stored.version);
+        if !expected_version.matches(current_version) {
+            return Err(SagaError::WrongExpectedVersion {
+                saga_name: instance.saga_name,
+                correlation_id: instance.correlation_id,
+                expected: expected_version,
+                actual: current_version,
+            });
+        }
+
+        instances.insert(key, SagaInstance { version: current_version + 1, ..instance });
+
+        Ok(())
+    }
+
+    fn expired(&self, saga_name: &str, now: SystemTime) -> Result<Vec<SagaInstance>, SagaError> {
+        let instances = self.instances.read().map_err(|e| SagaError::StorageError(e.to_string()))?;
+
+        Ok(
+            instances.values()
+                     .filter(|instance| instance.saga_name == saga_name && instance.status == SagaStatus::Running)
+                     .filter(|instance| instance.deadline.is_some_and(|deadline| deadline <= now))
+                     .cloned()
+                     .collect()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(version: u64) -> SagaInstance {
+        SagaInstance {
+            saga_name: "order_saga".to_string(),
+            correlation_id: "order-1".to_string(),
+            state: b"{}".to_vec(),
+            status: SagaStatus::Running,
+            deadline: None,
+            version,
+        }
+    }
+
+    #[test]
+    fn it_should_reject_saving_a_stale_instance() {
+        let store = InMemorySagaStore::new();
+
+        store.save(instance(0), ExpectedVersion::NoStream).unwrap();
+        let loaded = store.load("order_saga", "order-1").unwrap().unwrap();
+        assert_eq!(loaded.version, 1);
+
+        store.save(loaded.clone(), ExpectedVersion::Exact(loaded.version)).unwrap();
+        let stale = store.save(loaded, ExpectedVersion::Exact(1));
+
+        assert!(matches!(stale, Err(SagaError::WrongExpectedVersion { actual: 2, .. })));
+        assert_eq!(store.load("order_saga", "order-1").unwrap().unwrap().version, 2);
+    }
+}
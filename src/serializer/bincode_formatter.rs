@@ -0,0 +1,119 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::event::{Event, EventMetadata, EventWithMetadata};
+use crate::serializer::{EventDeserializer, EventSerializer, SnapshotSerializer};
+use crate::serializer::deserialized_event::{EventDeserializable, EventDeserializableData};
+use crate::serializer::error::{DeserializeError, SerializeError};
+
+///
+/// Serializes events to bincode, in the same data/meta envelope as `SerdeJSONEventFormatter`.
+///
+/// Bincode does not describe the data it encodes, so the attributes of the event are kept as JSON
+/// inside the envelope, letting them be deserialized into a `serde_json::Value` as the consumers do.
+///
+pub struct BincodeEventFormatter;
+
+#[derive(Serialize, Deserialize)]
+struct BincodeEnvelope {
+    data: BincodeEnvelopeData,
+    meta: EventMetadata,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BincodeEnvelopeData {
+    event_name: String,
+    attributes: Vec<u8>,
+}
+
+const METADATA_FIELD: &str = "metadata";
+
+impl EventSerializer for BincodeEventFormatter {
+    fn serialize<T: Event + EventWithMetadata + Serialize>(&self, event: &T) -> Result<Vec<u8>, SerializeError> {
+        let mut attributes = serde_json::to_value(event)
+            .map_err(|e| SerializeError::UnableToSerializeEvent(Box::new(e)))?;
+        if let Value::Object(attributes) = &mut attributes {
+            attributes.remove(METADATA_FIELD);
+        }
+
+        let envelope = BincodeEnvelope {
+            data: BincodeEnvelopeData {
+                event_name: event.event_name().to_string(),
+                attributes: serde_json::to_vec(&attributes).map_err(|e| SerializeError::UnableToSerializeEvent(Box::new(e)))?,
+            },
+            meta: event.metadata().clone(),
+        };
+
+        bincode::serialize(&envelope)
+            .map_err(|e| SerializeError::UnableToSerializeEvent(Box::new(e)))
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/x-bincode"
+    }
+}
+
+impl EventDeserializer for BincodeEventFormatter {
+    fn deserialize<T: DeserializeOwned + Serialize>(&self, raw_event: &[u8]) -> Result<EventDeserializable<T>, DeserializeError> {
+        let envelope = bincode::deserialize::<BincodeEnvelope>(raw_event)
+            .map_err(|e| DeserializeError::UnableToDeserializeEvent(Box::new(e)))?;
+
+        let mut attributes = serde_json::from_slice::<Value>(&envelope.data.attributes)
+            .map_err(|e| DeserializeError::UnableToDeserializeEvent(Box::new(e)))?;
+        if let Value::Object(attributes) = &mut attributes {
+            let meta = serde_json::to_value(envelope.meta).map_err(|e| DeserializeError::UnableToDeserializeEvent(Box::new(e)))?;
+            attributes.insert(METADATA_FIELD.to_string(), meta);
+        }
+
+        Ok(
+            EventDeserializable {
+                data: EventDeserializableData {
+                    event_name: envelope.data.event_name,
+                    attributes: serde_json::from_value(attributes).map_err(|e| DeserializeError::UnableToDeserializeEvent(Box::new(e)))?,
+                }
+            }
+        )
+    }
+}
+
+impl SnapshotSerializer for BincodeEventFormatter {
+    fn serialize_snapshot<T: Serialize>(&self, state: &T) -> Result<Vec<u8>, SerializeError> {
+        bincode::serialize(state)
+            .map_err(|e| SerializeError::UnableToSerializeEvent(Box::new(e)))
+    }
+
+    fn deserialize_snapshot<T: DeserializeOwned>(&self, raw_snapshot: &[u8]) -> Result<T, DeserializeError> {
+        bincode::deserialize::<T>(raw_snapshot)
+            .map_err(|e| DeserializeError::UnableToDeserializeEvent(Box::new(e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::serializer::fixtures::SerializableEvent;
+
+    use super::*;
+
+    #[test]
+    fn it_should_deserialize_a_serialized_event_with_its_metadata() {
+        let payload = BincodeEventFormatter.serialize(&SerializableEvent::correlated()).unwrap();
+
+        let deserialized = BincodeEventFormatter.deserialize::<SerializableEvent>(&payload).unwrap();
+
+        assert_eq!(deserialized.data.event_name, "serializable_event");
+        assert_eq!(deserialized.data.attributes.id, "1");
+        assert_eq!(deserialized.data.attributes.get_metadata("correlation-id").unwrap(), "abc");
+    }
+
+    #[test]
+    fn it_should_deserialize_a_serialized_event_into_a_json_value() {
+        let payload = BincodeEventFormatter.serialize(&SerializableEvent::correlated()).unwrap();
+
+        let deserialized = BincodeEventFormatter.deserialize::<Value>(&payload).unwrap();
+
+        assert_eq!(deserialized.data.event_name, "serializable_event");
+        assert_eq!(deserialized.data.attributes["id"], "1");
+        assert_eq!(deserialized.data.attributes["metadata"]["correlation-id"], "abc");
+    }
+}
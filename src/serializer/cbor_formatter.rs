@@ -0,0 +1,85 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::event::{Event, EventWithMetadata};
+use crate::serializer::{EventDeserializer, EventSerializer, SnapshotSerializer};
+use crate::serializer::deserialized_event::EventDeserializable;
+use crate::serializer::error::{DeserializeError, SerializeError};
+use crate::serializer::serialized_event::{EventSerializable, EventSerializableData};
+
+///
+/// Serializes events to CBOR, in the same data/meta envelope as `SerdeJSONEventFormatter`.
+///
+pub struct CborEventFormatter;
+
+impl EventSerializer for CborEventFormatter {
+    fn serialize<T: Event + EventWithMetadata + Serialize>(&self, event: &T) -> Result<Vec<u8>, SerializeError> {
+        let event_serializable = EventSerializable::new(
+            EventSerializableData::new(event.event_name(), event),
+            event.metadata()
+        );
+
+        let mut payload = vec![];
+        ciborium::into_writer(&event_serializable, &mut payload)
+            .map_err(|e| SerializeError::UnableToSerializeEvent(Box::new(e)))?;
+
+        Ok(payload)
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/cbor"
+    }
+}
+
+impl EventDeserializer for CborEventFormatter {
+    fn deserialize<T: DeserializeOwned + Serialize>(&self, raw_event: &[u8]) -> Result<EventDeserializable<T>, DeserializeError> {
+        ciborium::from_reader::<EventDeserializable<T>, _>(raw_event)
+            .map_err(|e| DeserializeError::UnableToDeserializeEvent(Box::new(e)))
+    }
+}
+
+impl SnapshotSerializer for CborEventFormatter {
+    fn serialize_snapshot<T: Serialize>(&self, state: &T) -> Result<Vec<u8>, SerializeError> {
+        let mut payload = vec![];
+        ciborium::into_writer(state, &mut payload)
+            .map_err(|e| SerializeError::UnableToSerializeEvent(Box::new(e)))?;
+
+        Ok(payload)
+    }
+
+    fn deserialize_snapshot<T: DeserializeOwned>(&self, raw_snapshot: &[u8]) -> Result<T, DeserializeError> {
+        ciborium::from_reader::<T, _>(raw_snapshot)
+            .map_err(|e| DeserializeError::UnableToDeserializeEvent(Box::new(e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::serializer::fixtures::SerializableEvent;
+
+    use super::*;
+
+    #[test]
+    fn it_should_deserialize_a_serialized_event_with_its_metadata() {
+        let payload = CborEventFormatter.serialize(&SerializableEvent::correlated()).unwrap();
+
+        let deserialized = CborEventFormatter.deserialize::<SerializableEvent>(&payload).unwrap();
+
+        assert_eq!(deserialized.data.event_name, "serializable_event");
+        assert_eq!(deserialized.data.attributes.id, "1");
+        assert_eq!(deserialized.data.attributes.get_metadata("correlation-id").unwrap(), "abc");
+    }
+
+    #[test]
+    fn it_should_deserialize_a_serialized_event_as_a_value() {
+        let payload = CborEventFormatter.serialize(&SerializableEvent::correlated()).unwrap();
+
+        let deserialized = CborEventFormatter.deserialize::<Value>(&payload).unwrap();
+
+        assert_eq!(deserialized.data.event_name, "serializable_event");
+        assert_eq!(deserialized.data.attributes["id"], "1");
+        assert_eq!(deserialized.data.attributes["metadata"]["correlation-id"], "abc");
+    }
+}
@@ -0,0 +1,462 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::bus::middleware::PUBLISHED_AT;
+use crate::event::{CORRELATION_ID, EVENT_ID, Event, EventWithMetadata, unique_id};
+use crate::serializer::{EventDeserializer, EventSerializer};
+use crate::serializer::deserialized_event::{EventDeserializable, EventDeserializableData};
+use crate::serializer::error::{DeserializeError, SerializeError};
+
+pub const SPEC_VERSION: &str = "1.0";
+
+///
+/// Prefix of the CloudEvents attributes in the headers of an AMQP message in binary mode.
+///
+pub const AMQP_HEADER_PREFIX: &str = "cloudEvents:";
+
+const DATA_CONTENT_TYPE: &str = "application/json";
+const CONTEXT_ATTRIBUTES: [&str; 10] = ["specversion", "id", "source", "type", "datacontenttype", "dataschema", "subject", "time", "data", "data_base64"];
+const METADATA_KEYS: [&str; 2] = [CORRELATION_ID, PUBLISHED_AT];
+
+#[derive(Debug)]
+pub enum CloudEventError {
+    MissingAttribute(&'static str),
+    UnsupportedSpecVersion(String),
+}
+
+impl Display for CloudEventError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CloudEventError::MissingAttribute(attribute) => write!(f, "Missing CloudEvents attribute {}", attribute),
+            CloudEventError::UnsupportedSpecVersion(version) => write!(f, "Unsupported CloudEvents version {}", version),
+        }
+    }
+}
+
+impl Error for CloudEventError {}
+
+///
+/// Serializes events as CloudEvents 1.0.
+///
+/// The event name is the `type`, the `event-id` metadata the `id`, and the rest of the metadata goes
+/// to extension attributes. The Rabbit and Postgres buses stamp the `event-id` of the events before
+/// publishing them, so that republishing an event keeps its `id`. Extension names only keep the lowercase
+/// letters and digits of the metadata keys, and the keys of this crate, like `correlation-id`,
+/// are restored when deserializing.
+///
+/// `EventSerializer` produces structured mode JSON, while `serialize_binary` keeps the attributes
+/// apart from the data for the AMQP binary mode. The Rabbit bus publishes in binary mode with
+/// `with_amqp_binary_mode`, and the consumers read both modes.
+///
+pub struct CloudEventsFormatter {
+    source: String,
+    generate_id: fn() -> String,
+    amqp_binary_mode: bool,
+}
+
+///
+/// A CloudEvent in binary mode: its attributes, without `data`, and the JSON data.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BinaryCloudEvent {
+    pub attributes: BTreeMap<String, String>,
+    pub data: Vec<u8>,
+}
+
+impl CloudEventsFormatter {
+    pub fn new(source: impl Into<String>) -> Self {
+        CloudEventsFormatter {
+            source: source.into(),
+            generate_id: unique_id,
+            amqp_binary_mode: false,
+        }
+    }
+
+    pub fn with_id_generator(mut self, generate_id: fn() -> String) -> Self {
+        self.generate_id = generate_id;
+        self
+    }
+
+    ///
+    /// Publish AMQP messages in binary mode, the attributes as `cloudEvents:` headers and the data as body.
+    ///
+    pub fn with_amqp_binary_mode(mut self) -> Self {
+        self.amqp_binary_mode = true;
+        self
+    }
+
+    pub fn serialize_binary<T: Event + EventWithMetadata + Serialize>(&self, event: &T) -> Result<BinaryCloudEvent, SerializeError> {
+        let attributes = self.attributes(event)?;
+        let data = serde_json::to_vec(&event_data(event)?)
+            .map_err(|e| SerializeError::UnableToSerializeEvent(Box::new(e)))?;
+
+        Ok(BinaryCloudEvent { attributes, data })
+    }
+
+    pub fn deserialize_binary<T: DeserializeOwned + Serialize>(&self, event: &BinaryCloudEvent) -> Result<EventDeserializable<T>, DeserializeError> {
+        let data = serde_json::from_slice::<Value>(&event.data)
+            .map_err(|e| DeserializeError::UnableToDeserializeEvent(Box::new(e)))?;
+
+        from_cloud_event(&event.attributes, data)
+    }
+
+    ///
+    /// Fails on a metadata key that would overwrite a context attribute or another extension once sanitized,
+    /// going through the keys in order so the same one is always reported.
+    ///
+    fn attributes<T: Event + EventWithMetadata>(&self, event: &T) -> Result<BTreeMap<String, String>, SerializeError> {
+        let mut metadata: Vec<_> = event.metadata().iter().collect();
+        metadata.sort();
+        let mut attributes = BTreeMap::new();
+
+        for (key, value) in metadata {
+            if key == EVENT_ID {
+                continue;
+            }
+
+            let extension = extension_name(key);
+            if extension.is_empty() || CONTEXT_ATTRIBUTES.contains(&extension.as_str()) || attributes.contains_key(&extension) {
+                return Err(SerializeError::UnableToSerializeEvent(
+                    format!("The metadata key {} is not a valid CloudEvents extension name", key).into()
+                ));
+            }
+
+            attributes.insert(extension, value.clone());
+        }
+
+        let id = event.metadata().get(EVENT_ID).cloned().unwrap_or_else(self.generate_id);
+        attributes.insert("specversion".to_string(), SPEC_VERSION.to_string());
+        attributes.insert("id".to_string(), id);
+        attributes.insert("source".to_string(), self.source.clone());
+        attributes.insert("type".to_string(), event.event_name().to_string());
+        attributes.insert("datacontenttype".to_string(), DATA_CONTENT_TYPE.to_string());
+
+        Ok(attributes)
+    }
+}
+
+impl EventSerializer for CloudEventsFormatter {
+    fn serialize<T: Event + EventWithMetadata + Serialize>(&self, event: &T) -> Result<Vec<u8>, SerializeError> {
+        let mut cloud_event: Map<String, Value> = self.attributes(event)?
+                                                      .into_iter()
+                                                      .map(|(name, value)| (name, Value::String(value)))
+                                                      .collect();
+        cloud_event.insert("data".to_string(), Value::Object(event_data(event)?));
+
+        serde_json::to_vec(&cloud_event)
+            .map_err(|e| SerializeError::UnableToSerializeEvent(Box::new(e)))
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/cloudevents+json"
+    }
+
+    fn stamp<T: EventWithMetadata>(&self, event: &mut T) {
+        if event.get_metadata(EVENT_ID).is_none() {
+            event.add_metadata(EVENT_ID.to_string(), (self.generate_id)());
+        }
+    }
+
+    #[cfg(feature = "rabbit")]
+    fn serialize_amqp<T: Event + EventWithMetadata + Serialize>(&self, event: &T) -> Result<(Vec<u8>, lapin::BasicProperties), SerializeError> {
+        if !self.amqp_binary_mode {
+            let properties = lapin::BasicProperties::default().with_content_type(self.content_type().into());
+            return Ok((self.serialize(event)?, properties));
+        }
+
+        let binary = self.serialize_binary(event)?;
+        let properties = binary.amqp_properties();
+
+        Ok((binary.data, properties))
+    }
+}
+
+impl EventDeserializer for CloudEventsFormatter {
+    fn deserialize<T: DeserializeOwned + Serialize>(&self, raw_event: &[u8]) -> Result<EventDeserializable<T>, DeserializeError> {
+        let mut cloud_event = serde_json::from_slice::<Map<String, Value>>(raw_event)
+            .map_err(|e| DeserializeError::UnableToDeserializeEvent(Box::new(e)))?;
+
+        let data = cloud_event.remove("data").unwrap_or(Value::Null);
+        let attributes = cloud_event.into_iter()
+                                    .map(|(name, value)| match value {
+                                        Value::String(value) => (name, value),
+                                        value => (name, value.to_string()),
+                                    })
+                                    .collect();
+
+        from_cloud_event(&attributes, data)
+    }
+
+    ///
+    /// Deserialize a message in binary mode when it has the `cloudEvents:specversion` header, and in structured mode otherwise.
+    ///
+    #[cfg(feature = "rabbit")]
+    fn deserialize_amqp<T: DeserializeOwned + Serialize>(&self, body: &[u8], properties: &lapin::BasicProperties) -> Result<EventDeserializable<T>, DeserializeError> {
+        let is_binary = properties.headers()
+                                  .as_ref()
+                                  .is_some_and(|headers| headers.inner().contains_key(format!("{}specversion", AMQP_HEADER_PREFIX).as_str()));
+
+        if !is_binary {
+            return self.deserialize(body);
+        }
+
+        self.deserialize_binary(&BinaryCloudEvent::from_amqp(body, properties))
+    }
+}
+
+#[cfg(feature = "rabbit")]
+impl BinaryCloudEvent {
+    ///
+    /// The AMQP properties of the message, with every attribute as a `cloudEvents:` header.
+    ///
+    pub fn amqp_properties(&self) -> lapin::BasicProperties {
+        use lapin::types::{AMQPValue, FieldTable, LongString, ShortString};
+
+        let mut headers = FieldTable::default();
+        for (name, value) in &self.attributes {
+            if name == "datacontenttype" {
+                continue;
+            }
+
+            headers.insert(ShortString::from(format!("{}{}", AMQP_HEADER_PREFIX, name)), AMQPValue::LongString(LongString::from(value.as_str())));
+        }
+
+        lapin::BasicProperties::default()
+            .with_content_type(ShortString::from(DATA_CONTENT_TYPE))
+            .with_headers(headers)
+    }
+
+    pub fn from_amqp(data: &[u8], properties: &lapin::BasicProperties) -> Self {
+        let mut attributes = BTreeMap::new();
+
+        if let Some(headers) = properties.headers() {
+            for (name, value) in headers.inner() {
+                let Some(name) = name.as_str().strip_prefix(AMQP_HEADER_PREFIX) else {
+                    continue;
+                };
+
+                let value = match value.as_long_string() {
+                    Some(value) => value.to_string(),
+                    None => continue,
+                };
+
+                attributes.insert(name.to_string(), value);
+            }
+        }
+
+        if let Some(content_type) = properties.content_type() {
+            attributes.insert("datacontenttype".to_string(), content_type.to_string());
+        }
+
+        BinaryCloudEvent {
+            attributes,
+            data: data.to_vec(),
+        }
+    }
+}
+
+fn event_data<T: Serialize>(event: &T) -> Result<Map<String, Value>, SerializeError> {
+    let data = serde_json::to_value(event)
+        .map_err(|e| SerializeError::UnableToSerializeEvent(Box::new(e)))?;
+
+    let mut data = match data {
+        Value::Object(data) => data,
+        _ => Map::new(),
+    };
+    data.remove("metadata");
+
+    Ok(data)
+}
+
+fn from_cloud_event<T: DeserializeOwned + Serialize>(attributes: &BTreeMap<String, String>, data: Value) -> Result<EventDeserializable<T>, DeserializeError> {
+    let attribute = |name: &'static str| {
+        attributes.get(name)
+                  .ok_or_else(|| DeserializeError::UnableToDeserializeEvent(Box::new(CloudEventError::MissingAttribute(name))))
+    };
+
+    let spec_version = attribute("specversion")?;
+    if spec_version != SPEC_VERSION {
+        return Err(DeserializeError::UnableToDeserializeEvent(Box::new(CloudEventError::UnsupportedSpecVersion(spec_version.clone()))));
+    }
+
+    let event_name = attribute("type")?.clone();
+
+    let mut metadata: Map<String, Value> = attributes.iter()
+                                                     .filter(|(name, _)| !CONTEXT_ATTRIBUTES.contains(&name.as_str()))
+                                                     .map(|(name, value)| (metadata_key(name), Value::String(value.clone())))
+                                                     .collect();
+    metadata.insert(EVENT_ID.to_string(), Value::String(attribute("id")?.clone()));
+
+    let mut data = match data {
+        Value::Object(data) => data,
+        _ => Map::new(),
+    };
+    data.insert("metadata".to_string(), Value::Object(metadata));
+
+    let attributes = serde_json::from_value::<T>(Value::Object(data))
+        .map_err(|e| DeserializeError::UnableToDeserializeEvent(Box::new(e)))?;
+
+    Ok(
+        EventDeserializable {
+            data: EventDeserializableData {
+                event_name,
+                attributes,
+            }
+        }
+    )
+}
+
+fn extension_name(key: &str) -> String {
+    key.chars()
+       .filter(|c| c.is_ascii_alphanumeric())
+       .map(|c| c.to_ascii_lowercase())
+       .collect()
+}
+
+fn metadata_key(extension: &str) -> String {
+    METADATA_KEYS.iter()
+                 .find(|key| extension_name(key) == extension)
+                 .map_or_else(|| extension.to_string(), |key| key.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::serializer::fixtures::SerializableEvent;
+
+    use super::*;
+
+    fn serializable_event() -> SerializableEvent {
+        let mut event = SerializableEvent::correlated();
+        event.add_metadata(EVENT_ID.to_string(), "event-1".to_string());
+        event
+    }
+
+    #[test]
+    fn it_should_serialize_a_structured_cloud_event() {
+        let formatter = CloudEventsFormatter::new("/orders");
+
+        let payload = formatter.serialize(&serializable_event()).unwrap();
+
+        let cloud_event: Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(cloud_event, serde_json::json!({
+            "specversion": "1.0",
+            "id": "event-1",
+            "source": "/orders",
+            "type": "serializable_event",
+            "datacontenttype": "application/json",
+            "correlationid": "abc",
+            "data": { "id": "1" }
+        }));
+    }
+
+    #[test]
+    fn it_should_deserialize_a_structured_cloud_event_with_its_metadata() {
+        let formatter = CloudEventsFormatter::new("/orders");
+        let payload = formatter.serialize(&serializable_event()).unwrap();
+
+        let deserialized = formatter.deserialize::<SerializableEvent>(&payload).unwrap();
+
+        assert_eq!(deserialized.data.event_name, "serializable_event");
+        assert_eq!(deserialized.data.attributes.id, "1");
+        assert_eq!(deserialized.data.attributes.get_metadata(EVENT_ID).unwrap(), "event-1");
+        assert_eq!(deserialized.data.attributes.get_metadata(CORRELATION_ID).unwrap(), "abc");
+    }
+
+    #[test]
+    fn it_should_generate_the_id_of_events_without_one() {
+        let formatter = CloudEventsFormatter::new("/orders").with_id_generator(|| "generated".to_string());
+        let event = SerializableEvent::new("1");
+
+        let binary = formatter.serialize_binary(&event).unwrap();
+
+        assert_eq!(binary.attributes.get("id").unwrap(), "generated");
+        assert_eq!(binary.data, b"{\"id\":\"1\"}");
+    }
+
+    #[test]
+    fn it_should_not_deserialize_an_unsupported_spec_version() {
+        let formatter = CloudEventsFormatter::new("/orders");
+        let payload = br#"{"specversion":"0.3","id":"1","source":"/orders","type":"serializable_event","data":{"id":"1"}}"#;
+
+        let Err(error) = formatter.deserialize::<SerializableEvent>(payload) else {
+            panic!("The event should not be deserialized");
+        };
+
+        assert_eq!(error.to_string(), "Unable to deserialize event: Unsupported CloudEvents version 0.3");
+    }
+
+    #[test]
+    fn it_should_not_serialize_metadata_that_would_overwrite_the_envelope() {
+        let formatter = CloudEventsFormatter::new("/orders");
+
+        for key in ["type", "Spec-Version", "--", "correlation_id"] {
+            let mut event = serializable_event();
+            event.add_metadata(key.to_string(), "overwritten".to_string());
+
+            let Err(error) = formatter.serialize(&event) else {
+                panic!("The metadata key {} should be rejected", key);
+            };
+
+            assert_eq!(
+                error.to_string(),
+                format!("Unable to serialize event: The metadata key {} is not a valid CloudEvents extension name", key)
+            );
+        }
+    }
+
+    #[cfg(feature = "rabbit")]
+    #[test]
+    fn it_should_round_trip_a_binary_cloud_event_through_amqp_properties() {
+        let formatter = CloudEventsFormatter::new("/orders");
+        let binary = formatter.serialize_binary(&serializable_event()).unwrap();
+
+        let properties = binary.amqp_properties();
+        let headers = properties.headers().as_ref().unwrap();
+        assert!(headers.inner().contains_key("cloudEvents:type"));
+        assert_eq!(properties.content_type().as_ref().unwrap().as_str(), "application/json");
+
+        let received = BinaryCloudEvent::from_amqp(&binary.data, &properties);
+        assert_eq!(received, binary);
+
+        let deserialized = formatter.deserialize_binary::<SerializableEvent>(&received).unwrap();
+        assert_eq!(deserialized.data.attributes.get_metadata(CORRELATION_ID).unwrap(), "abc");
+    }
+
+    #[test]
+    fn it_should_stamp_the_id_of_events_without_one_once() {
+        let formatter = CloudEventsFormatter::new("/orders").with_id_generator(|| "generated".to_string());
+        let mut event = SerializableEvent::new("1");
+        let mut identified_event = serializable_event();
+
+        formatter.stamp(&mut event);
+        formatter.stamp(&mut identified_event);
+
+        assert_eq!(event.get_metadata(EVENT_ID).unwrap(), "generated");
+        assert_eq!(identified_event.get_metadata(EVENT_ID).unwrap(), "event-1");
+    }
+
+    #[cfg(feature = "rabbit")]
+    #[test]
+    fn it_should_read_the_amqp_messages_of_both_modes() {
+        let structured = CloudEventsFormatter::new("/orders");
+        let binary = CloudEventsFormatter::new("/orders").with_amqp_binary_mode();
+
+        for formatter in [&structured, &binary] {
+            let (body, properties) = formatter.serialize_amqp(&serializable_event()).unwrap();
+
+            let deserialized = structured.deserialize_amqp::<Value>(&body, &properties).unwrap();
+
+            assert_eq!(deserialized.data.event_name, "serializable_event");
+            assert_eq!(deserialized.data.attributes["id"], "1");
+            assert_eq!(deserialized.data.attributes["metadata"][EVENT_ID], "event-1");
+        }
+
+        let (body, _) = binary.serialize_amqp(&serializable_event()).unwrap();
+        assert_eq!(body, b"{\"id\":\"1\"}");
+    }
+}
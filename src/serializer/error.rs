@@ -3,30 +3,42 @@ use std::fmt::{Display, Formatter};
 
 #[derive(Debug)]
 pub enum SerializeError {
-    UnableToSerializeEvent
+    UnableToSerializeEvent(Box<dyn Error + Send + Sync>),
 }
 
 impl Display for SerializeError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            SerializeError::UnableToSerializeEvent => write!(f, "Unable to serialize event"),
+            SerializeError::UnableToSerializeEvent(error) => write!(f, "Unable to serialize event: {}", error),
         }
     }
 }
 
-impl Error for SerializeError {}
+impl Error for SerializeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SerializeError::UnableToSerializeEvent(error) => Some(error.as_ref()),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub enum DeserializeError {
-    UnableToDeserializeEvent
+    UnableToDeserializeEvent(Box<dyn Error + Send + Sync>),
 }
 
 impl Display for DeserializeError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            DeserializeError::UnableToDeserializeEvent => write!(f, "Unable to deserialize event"),
+            DeserializeError::UnableToDeserializeEvent(error) => write!(f, "Unable to deserialize event: {}", error),
         }
     }
 }
 
-impl Error for DeserializeError {}
+impl Error for DeserializeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DeserializeError::UnableToDeserializeEvent(error) => Some(error.as_ref()),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::mem;
+
+use serde::{Deserialize, Serialize};
+
+use crate::event::{CORRELATION_ID, Event, EventMetadata, EventWithMetadata};
+
+///
+/// The event the formatters are tested with.
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct SerializableEvent {
+    pub(crate) id: String,
+    pub(crate) metadata: EventMetadata
+}
+
+impl SerializableEvent {
+    pub(crate) fn new(id: &str) -> Self {
+        SerializableEvent { id: id.to_string(), metadata: EventMetadata::default() }
+    }
+
+    ///
+    /// An event correlated with `abc`.
+    ///
+    pub(crate) fn correlated() -> Self {
+        let mut event = Self::new("1");
+        event.add_metadata(CORRELATION_ID.to_string(), "abc".to_string());
+        event
+    }
+}
+
+impl Event for SerializableEvent {
+    fn event_name(&self) -> &'static str {
+        "serializable_event"
+    }
+}
+
+impl EventWithMetadata for SerializableEvent {
+    fn add_metadata(&mut self, key: String, value: String) {
+        self.metadata.add(key, value);
+    }
+
+    fn get_metadata(&self, key: &str) -> Option<&String> {
+        self.metadata.get(key)
+    }
+
+    fn metadata(&self) -> &EventMetadata {
+        &self.metadata
+    }
+
+    fn drain_metadata(&mut self) -> EventMetadata {
+        mem::take(&mut self.metadata)
+    }
+}
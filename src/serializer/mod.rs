@@ -6,15 +6,67 @@ use crate::serializer::deserialized_event::EventDeserializable;
 use crate::serializer::error::{DeserializeError, SerializeError};
 
 pub mod serde_formatter;
+pub mod cloud_events_formatter;
 pub mod deserialized_event;
 
-mod error;
+#[cfg(feature = "msgpack")]
+pub mod msgpack_formatter;
+#[cfg(feature = "cbor")]
+pub mod cbor_formatter;
+#[cfg(feature = "bincode")]
+pub mod bincode_formatter;
+
+pub mod error;
 mod serialized_event;
 
+#[cfg(test)]
+pub(crate) mod fixtures;
+
+///
+/// Encodes an event with its name and metadata, in the format reported by `content_type`.
+///
 pub trait EventSerializer: Send + Sync + 'static {
-    fn serialize<T: Event + EventWithMetadata + Serialize>(&self, event: &T) -> Result<String, SerializeError>;
+    fn serialize<T: Event + EventWithMetadata + Serialize>(&self, event: &T) -> Result<Vec<u8>, SerializeError>;
+
+    ///
+    /// The MIME type of the serialized events, e.g. for the `content_type` AMQP property.
+    ///
+    fn content_type(&self) -> &'static str;
+
+    ///
+    /// Complete the metadata the format needs before the event is published, so that it is kept
+    /// with the event instead of being generated on every `serialize`.
+    ///
+    fn stamp<T: EventWithMetadata>(&self, _event: &mut T) {}
+
+    ///
+    /// The body and the properties of the AMQP message of the event, the serialized event with its content type by default.
+    ///
+    #[cfg(feature = "rabbit")]
+    fn serialize_amqp<T: Event + EventWithMetadata + Serialize>(&self, event: &T) -> Result<(Vec<u8>, lapin::BasicProperties), SerializeError> {
+        let properties = lapin::BasicProperties::default().with_content_type(self.content_type().into());
+
+        Ok((self.serialize(event)?, properties))
+    }
 }
 
 pub trait EventDeserializer: Send + Sync + 'static {
-    fn deserialize<T: DeserializeOwned + Serialize>(&self, raw_event: String) -> Result<EventDeserializable<T>, DeserializeError>;
-}
\ No newline at end of file
+    fn deserialize<T: DeserializeOwned + Serialize>(&self, raw_event: &[u8]) -> Result<EventDeserializable<T>, DeserializeError>;
+
+    ///
+    /// Deserialize the event of an AMQP message, only from its body by default.
+    ///
+    #[cfg(feature = "rabbit")]
+    fn deserialize_amqp<T: DeserializeOwned + Serialize>(&self, body: &[u8], _properties: &lapin::BasicProperties) -> Result<EventDeserializable<T>, DeserializeError> {
+        self.deserialize(body)
+    }
+}
+
+///
+/// Serializes the state of an aggregate to be stored as a snapshot.
+///
+pub trait SnapshotSerializer: Send + Sync + 'static {
+    fn serialize_snapshot<T: Serialize>(&self, state: &T) -> Result<Vec<u8>, SerializeError>;
+
+    fn deserialize_snapshot<T: DeserializeOwned>(&self, raw_snapshot: &[u8]) -> Result<T, DeserializeError>;
+}
@@ -0,0 +1,79 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::event::{Event, EventWithMetadata};
+use crate::serializer::{EventDeserializer, EventSerializer, SnapshotSerializer};
+use crate::serializer::deserialized_event::EventDeserializable;
+use crate::serializer::error::{DeserializeError, SerializeError};
+use crate::serializer::serialized_event::{EventSerializable, EventSerializableData};
+
+///
+/// Serializes events to MessagePack, in the same data/meta envelope as `SerdeJSONEventFormatter`.
+///
+pub struct MessagePackEventFormatter;
+
+impl EventSerializer for MessagePackEventFormatter {
+    fn serialize<T: Event + EventWithMetadata + Serialize>(&self, event: &T) -> Result<Vec<u8>, SerializeError> {
+        let event_serializable = EventSerializable::new(
+            EventSerializableData::new(event.event_name(), event),
+            event.metadata()
+        );
+
+        rmp_serde::to_vec_named(&event_serializable)
+            .map_err(|e| SerializeError::UnableToSerializeEvent(Box::new(e)))
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/msgpack"
+    }
+}
+
+impl EventDeserializer for MessagePackEventFormatter {
+    fn deserialize<T: DeserializeOwned + Serialize>(&self, raw_event: &[u8]) -> Result<EventDeserializable<T>, DeserializeError> {
+        rmp_serde::from_slice::<EventDeserializable<T>>(raw_event)
+            .map_err(|e| DeserializeError::UnableToDeserializeEvent(Box::new(e)))
+    }
+}
+
+impl SnapshotSerializer for MessagePackEventFormatter {
+    fn serialize_snapshot<T: Serialize>(&self, state: &T) -> Result<Vec<u8>, SerializeError> {
+        rmp_serde::to_vec_named(state)
+            .map_err(|e| SerializeError::UnableToSerializeEvent(Box::new(e)))
+    }
+
+    fn deserialize_snapshot<T: DeserializeOwned>(&self, raw_snapshot: &[u8]) -> Result<T, DeserializeError> {
+        rmp_serde::from_slice::<T>(raw_snapshot)
+            .map_err(|e| DeserializeError::UnableToDeserializeEvent(Box::new(e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::serializer::fixtures::SerializableEvent;
+
+    use super::*;
+
+    #[test]
+    fn it_should_deserialize_a_serialized_event_with_its_metadata() {
+        let payload = MessagePackEventFormatter.serialize(&SerializableEvent::correlated()).unwrap();
+
+        let deserialized = MessagePackEventFormatter.deserialize::<SerializableEvent>(&payload).unwrap();
+
+        assert_eq!(deserialized.data.event_name, "serializable_event");
+        assert_eq!(deserialized.data.attributes.id, "1");
+        assert_eq!(deserialized.data.attributes.get_metadata("correlation-id").unwrap(), "abc");
+    }
+
+    #[test]
+    fn it_should_deserialize_a_serialized_event_as_a_value() {
+        let payload = MessagePackEventFormatter.serialize(&SerializableEvent::correlated()).unwrap();
+
+        let deserialized = MessagePackEventFormatter.deserialize::<Value>(&payload).unwrap();
+
+        assert_eq!(deserialized.data.event_name, "serializable_event");
+        assert_eq!(deserialized.data.attributes["id"], "1");
+        assert_eq!(deserialized.data.attributes["metadata"]["correlation-id"], "abc");
+    }
+}
@@ -2,7 +2,7 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::event::{Event, EventWithMetadata};
-use crate::serializer::{EventDeserializer, EventSerializer};
+use crate::serializer::{EventDeserializer, EventSerializer, SnapshotSerializer};
 use crate::serializer::deserialized_event::EventDeserializable;
 use crate::serializer::error::{DeserializeError, SerializeError};
 use crate::serializer::serialized_event::{EventSerializable, EventSerializableData};
@@ -10,78 +10,61 @@ use crate::serializer::serialized_event::{EventSerializable, EventSerializableDa
 pub struct SerdeJSONEventFormatter;
 
 impl EventSerializer for SerdeJSONEventFormatter {
-    fn serialize<T: Event + EventWithMetadata + Serialize>(&self, event: &T) -> Result<String, SerializeError> {
+    fn serialize<T: Event + EventWithMetadata + Serialize>(&self, event: &T) -> Result<Vec<u8>, SerializeError> {
         let event_serializable = EventSerializable::new(
             EventSerializableData::new(event.event_name(), event),
             event.metadata()
         );
 
-        serde_json::to_string(&event_serializable)
-            .map_err(|_| SerializeError::UnableToSerializeEvent)
+        serde_json::to_vec(&event_serializable)
+            .map_err(|e| SerializeError::UnableToSerializeEvent(Box::new(e)))
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/json"
     }
 }
 
 impl EventDeserializer for SerdeJSONEventFormatter {
-    fn deserialize<T: DeserializeOwned + Serialize>(&self, raw_event: String) -> Result<EventDeserializable<T>, DeserializeError> {
-        serde_json::from_str::<EventDeserializable<T>>(&raw_event)
-            .map_err(|_| DeserializeError::UnableToDeserializeEvent)
+    fn deserialize<T: DeserializeOwned + Serialize>(&self, raw_event: &[u8]) -> Result<EventDeserializable<T>, DeserializeError> {
+        serde_json::from_slice::<EventDeserializable<T>>(raw_event)
+            .map_err(|e| DeserializeError::UnableToDeserializeEvent(Box::new(e)))
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use std::mem;
-
-    use serde::Deserialize;
-
-    use crate::event::EventMetadata;
-
-    use super::*;
-
-    #[derive(Serialize, Deserialize, Debug)]
-    struct SerializableEvent {
-        id: String,
-        metadata: EventMetadata
+impl SnapshotSerializer for SerdeJSONEventFormatter {
+    fn serialize_snapshot<T: Serialize>(&self, state: &T) -> Result<Vec<u8>, SerializeError> {
+        serde_json::to_vec(state)
+            .map_err(|e| SerializeError::UnableToSerializeEvent(Box::new(e)))
     }
 
-    impl Event for SerializableEvent {
-        fn event_name(&self) -> &'static str {
-            "serializable_event"
-        }
+    fn deserialize_snapshot<T: DeserializeOwned>(&self, raw_snapshot: &[u8]) -> Result<T, DeserializeError> {
+        serde_json::from_slice::<T>(raw_snapshot)
+            .map_err(|e| DeserializeError::UnableToDeserializeEvent(Box::new(e)))
     }
+}
 
-    impl EventWithMetadata for SerializableEvent {
-        fn add_metadata(&mut self, key: String, value: String) {
-            self.metadata.add(key, value);
-        }
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
 
-        fn get_metadata(&self, key: &str) -> Option<&String> {
-            self.metadata.get(key)
-        }
+    use crate::serializer::fixtures::SerializableEvent;
 
-        fn metadata(&self) -> &EventMetadata {
-            &self.metadata
-        }
+    use super::*;
 
-        fn drain_metadata(&mut self) -> EventMetadata {
-            mem::take(&mut self.metadata)
-        }
-    }
-    
     #[test]
     fn it_should_serialize_event_and_add_event_name() {
-        let event = SerializableEvent { id: "1".to_string(), metadata: EventMetadata::default() };
+        let event = SerializableEvent::new("1");
         let serialized = SerdeJSONEventFormatter.serialize(&event);
 
-        assert_eq!(serialized.unwrap(), "{\"data\":{\"type\":\"serializable_event\",\"attributes\":{\"id\":\"1\"}},\"meta\":{}}")
+        assert_eq!(serialized.unwrap(), b"{\"data\":{\"type\":\"serializable_event\",\"attributes\":{\"id\":\"1\"}},\"meta\":{}}")
     }
 
     #[test]
     fn it_should_deserialize_event_and_add_event_name() {
-        let json = "{\"data\":{\"type\":\"serializable_event\",\"attributes\":{\"id\":\"1\"}},\"meta\":{}}".to_string();
-
-        let deserialized = SerdeJSONEventFormatter.deserialize::<SerializableEvent>(json);
+        let json = "{\"data\":{\"type\":\"serializable_event\",\"attributes\":{\"id\":\"1\"}},\"meta\":{}}";
 
+        let deserialized = SerdeJSONEventFormatter.deserialize::<SerializableEvent>(json.as_bytes());
 
         assert!(deserialized.is_ok());
         let deserializable = deserialized.unwrap();
@@ -92,11 +75,22 @@ mod tests {
 
     #[test]
     fn it_should_not_deserialize_event_when_json_is_not_equals() {
-        let json = "{\"data\":{\"type\":\"serializable_event\",\"attributes\":{\"idd\":\"1\"}},\"meta\":{}}".to_string();
-
-        let deserialized = SerdeJSONEventFormatter.deserialize::<SerializableEvent>(json);
+        let json = "{\"data\":{\"type\":\"serializable_event\",\"attributes\":{\"idd\":\"1\"}},\"meta\":{}}";
 
+        let deserialized = SerdeJSONEventFormatter.deserialize::<SerializableEvent>(json.as_bytes());
 
         assert!(deserialized.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn it_should_keep_the_serde_error_when_deserialization_fails() {
+        let json = "{\"data\":{\"type\":\"serializable_event\",\"attributes\":{\"idd\":\"1\"}},\"meta\":{}}";
+
+        let Err(error) = SerdeJSONEventFormatter.deserialize::<SerializableEvent>(json.as_bytes()) else {
+            panic!("The event should not be deserialized");
+        };
+
+        assert!(error.source().unwrap().to_string().contains("missing field `id`"));
+        assert!(error.to_string().starts_with("Unable to deserialize event: missing field `id`"));
+    }
+}
@@ -1,5 +1,6 @@
 use std::error::Error;
 use std::fmt::Display;
+use std::time::Duration;
 
 use crate::event::Event;
 
@@ -12,10 +13,101 @@ pub trait AsyncSubscriber<T: Event>: Send + Sync + 'static {
     fn handle_event(&self, event: &T) -> impl std::future::Future<Output = Result<(), SubscriberError>> + Send;
 }
 
+///
+/// A subscriber that receives events of any type, used for catch-all and pattern registrations.
+///
+pub trait AnySubscriber {
+    fn handle_any(&self, event: &dyn Event) -> Result<(), SubscriberError>;
+}
+
+#[cfg(feature = "async")]
+pub trait AsyncAnySubscriber: Send + Sync + 'static {
+    fn handle_any(&self, event: &dyn Event) -> impl std::future::Future<Output = Result<(), SubscriberError>> + Send;
+}
+
+///
+/// Why a subscriber failed, and what should happen to the event.
+///
 #[derive(Debug)]
 pub enum SubscriberError {
+    ///
+    /// The event can never be handled and is discarded.
+    ///
     UnrecoverableError,
-    Inner(Box<dyn Error>),
+    ///
+    /// The event is retried with the default delay.
+    ///
+    Inner(Box<dyn Error + Send + Sync>),
+    ///
+    /// The event is retried, after the delay when there is one.
+    ///
+    Retryable {
+        error: Box<dyn Error + Send + Sync>,
+        delay: Option<Duration>,
+        code: Option<&'static str>,
+    },
+    ///
+    /// The event is not retried and goes straight to the dead letters.
+    ///
+    Permanent {
+        error: Box<dyn Error + Send + Sync>,
+        code: Option<&'static str>,
+    },
+    ///
+    /// The event is delivered again without counting as an attempt, e.g. while the subscriber shuts down.
+    ///
+    Requeue,
+}
+
+impl SubscriberError {
+    pub fn retryable(error: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        SubscriberError::Retryable { error: error.into(), delay: None, code: None }
+    }
+
+    pub fn permanent(error: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        SubscriberError::Permanent { error: error.into(), code: None }
+    }
+
+    ///
+    /// Retry the event after the delay. Only retryable errors have a delay.
+    ///
+    pub fn with_delay(self, delay: Duration) -> Self {
+        match self {
+            SubscriberError::Inner(error) => SubscriberError::Retryable { error, delay: Some(delay), code: None },
+            SubscriberError::Retryable { error, code, .. } => SubscriberError::Retryable { error, delay: Some(delay), code },
+            error => error,
+        }
+    }
+
+    ///
+    /// Tag the error with a code, e.g. for metrics. Only retryable and permanent errors have a code.
+    ///
+    pub fn with_code(self, code: &'static str) -> Self {
+        match self {
+            SubscriberError::Inner(error) => SubscriberError::Retryable { error, delay: None, code: Some(code) },
+            SubscriberError::Retryable { error, delay, .. } => SubscriberError::Retryable { error, delay, code: Some(code) },
+            SubscriberError::Permanent { error, .. } => SubscriberError::Permanent { error, code: Some(code) },
+            error => error,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, SubscriberError::Inner(_) | SubscriberError::Retryable { .. } | SubscriberError::Requeue)
+    }
+
+    pub fn delay(&self) -> Option<Duration> {
+        match self {
+            SubscriberError::Retryable { delay, .. } => *delay,
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            SubscriberError::Retryable { code, .. } | SubscriberError::Permanent { code, .. } => *code,
+            _ => None,
+        }
+    }
 }
 
 impl Display for SubscriberError {
@@ -23,8 +115,45 @@ impl Display for SubscriberError {
         match self {
             SubscriberError::UnrecoverableError => write!(f, "Unrecoverable error"),
             SubscriberError::Inner(e) => write!(f, "Inner error: {}", e),
+            SubscriberError::Retryable { error, .. } => write!(f, "Retryable error: {}", error),
+            SubscriberError::Permanent { error, .. } => write!(f, "Permanent error: {}", error),
+            SubscriberError::Requeue => write!(f, "Requeued"),
+        }
+    }
+}
+
+impl Error for SubscriberError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SubscriberError::Inner(error)
+            | SubscriberError::Retryable { error, .. }
+            | SubscriberError::Permanent { error, .. } => Some(error.as_ref()),
+            _ => None,
         }
     }
 }
 
-impl Error for SubscriberError {}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_keep_the_retry_hints_of_an_error() {
+        let error = SubscriberError::Inner("Rate limited".into())
+            .with_delay(Duration::from_secs(30))
+            .with_code("rate_limited");
+
+        assert!(error.is_retryable());
+        assert_eq!(error.delay(), Some(Duration::from_secs(30)));
+        assert_eq!(error.code(), Some("rate_limited"));
+        assert_eq!(error.source().unwrap().to_string(), "Rate limited");
+    }
+
+    #[test]
+    fn it_should_not_retry_permanent_errors() {
+        let error = SubscriberError::permanent("Invalid payload").with_delay(Duration::from_secs(30));
+
+        assert!(!error.is_retryable());
+        assert_eq!(error.delay(), None);
+    }
+}